@@ -1,10 +1,10 @@
 #![allow(non_snake_case)]
+#![allow(clippy::too_many_arguments)]
 #![no_std]
 use soroban_sdk::{
-    contract, contractimpl, contracttype, 
-    log, symbol_short, Address, Env, 
-    Symbol, String, Vec,
-    // Removed Map as it was unused
+    contract, contracterror, contractimpl, contracttype, 
+    log, symbol_short, token, Address, BytesN, Env, 
+    IntoVal, Map, Symbol, String, TryFromVal, Val, Vec,
 };
 
 // Asset status tracking
@@ -15,8 +15,8 @@ pub struct AssetStats {
     pub active_leases: u64,           // Number of currently active leases
     pub completed_leases: u64,        // Number of completed leases
     pub disputed_leases: u64,         // Number of disputed leases
-    pub total_value_locked: i128,     // Total XLM value locked in contracts
-    pub total_earnings: i128,         // Total earnings generated from leases
+    pub total_value_locked: Map<Address, i128>, // Value held in escrow for active leases, per token
+    pub total_earnings: Map<Address, i128>,     // Earnings generated from leases, per token
 }
 
 // Key for accessing global stats - Shortened to 9 chars max
@@ -28,21 +28,117 @@ const ASSET_CNT: Symbol = symbol_short!("ASSET_CNT");
 // Counter for unique lease IDs - Shortened to 9 chars max
 const LEASE_CNT: Symbol = symbol_short!("LEASE_CNT");
 
+// Migration cursor for rewriting legacy entries into the current layout
+const MIG_CUR: Symbol = symbol_short!("MIG_CUR");
+
+// Storage layout version: 1 is the original layout (instance storage, boolean lease
+// flags, no payment token), 2 kept roles and the arbitrator pool in instance storage,
+// 3 is the current one. Bump when the layout changes and teach migrate how to move
+// or fill the affected entries.
+const SCHEMA_VER: Symbol = symbol_short!("SCHEMA");
+const SCHEMA_VERSION: u32 = 3;
+
+// Largest dispute award, as a percentage of the security deposit
+const MAX_PENALTY_PERCENTAGE: u64 = 1_000;
+
+// Time the other party has to respond during a return before it auto-confirms
+const RETURN_CONFIRM_PERIOD: u64 = 3 * 24 * 60 * 60;
+
+// Most evidence entries a single dispute can hold
+const MAX_EVIDENCE: u32 = 20;
+
+// Largest page returned by listing queries, and most IDs scanned per call
+const MAX_PAGE_SIZE: u32 = 50;
+const MAX_PAGE_SCAN: u64 = 200;
+
+// Persistent entry TTL in ledgers: extended to ~30 days once below ~15 days
+const PERSISTENT_TTL_THRESHOLD: u32 = 259_200;
+const PERSISTENT_TTL_EXTEND: u32 = 518_400;
+
+// Contract administrator and pending admin during a two-step handover
+const ADMIN: Symbol = symbol_short!("ADMIN");
+const PEND_ADMN: Symbol = symbol_short!("PEND_ADMN");
+
+// Emergency pause state
+const PAUSED: Symbol = symbol_short!("PAUSED");
+
+// Arbitration settings and the total stake held; the pool of staked arbitrators
+// lives in persistent storage, under ARB_POOL in instance storage before version 3
+const ARB_CFG: Symbol = symbol_short!("ARB_CFG");
+const ARB_POOL: Symbol = symbol_short!("ARB_POOL");
+const ARB_STAKE: Symbol = symbol_short!("ARB_STAKE");
+
+// Event topics: (asset, <action>, asset_id) -> Asset, (lease, <action>, lease_id) -> Lease,
+// (dispute, <action>, lease_id) -> Lease, (stats, updated) -> AssetStats,
+// (role, <action>, account) -> Role, (dispute, voted, lease_id) -> (Address, Vote),
+// (dispute, evidence, lease_id) -> Evidence, (dispute, debt, lease_id) -> Debt,
+// (arbiter, <action>, account) -> stake, (pause, updated) -> PauseState,
+// (upgrade, wasm) -> BytesN<32>, (upgrade, migrated) -> schema version
+const EVT_ASSET: Symbol = symbol_short!("asset");
+const EVT_LEASE: Symbol = symbol_short!("lease");
+const EVT_DISPUTE: Symbol = symbol_short!("dispute");
+const EVT_STATS: Symbol = symbol_short!("stats");
+const EVT_ROLE: Symbol = symbol_short!("role");
+const EVT_ARBITER: Symbol = symbol_short!("arbiter");
+const EVT_PAUSE: Symbol = symbol_short!("pause");
+const EVT_UPGRADE: Symbol = symbol_short!("upgrade");
+
+// Number of seconds in a lease day
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+// Basis points in 100%
+const BPS_SCALE: i128 = 10_000;
+
+// Fixed-point scale for per-second rent rates
+const RATE_SCALE: i128 = 1_000_000_000;
+
+// Progress of migrate through the asset and lease ID ranges
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct MigrationCursor {
+    pub next_asset_id: u64,           // Next asset ID to move
+    pub next_lease_id: u64,           // Next lease ID to move
+}
+
 // Asset data structure
 #[contracttype]
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Asset {
     pub asset_id: u64,                // Unique identifier for the asset
     pub owner: Address,               // Asset owner's address
     pub title: String,                // Asset title/name
     pub description: String,          // Asset description
-    pub asset_value: i128,            // Asset value in XLM
-    pub daily_rate: i128,             // Daily leasing rate in XLM
+    pub token: Address,               // Payment token (native XLM SAC or any SEP-41 token)
+    pub asset_value: i128,            // Asset value in the payment token
+    pub daily_rate: i128,             // Daily leasing rate in the payment token
     pub available: bool,              // Availability status
     pub min_lease_days: u64,          // Minimum lease period in days
     pub max_lease_days: u64,          // Maximum lease period in days
-    pub security_deposit: i128,       // Required security deposit in XLM
+    pub security_deposit: i128,       // Required security deposit in the payment token
     pub created_at: u64,              // Asset registration timestamp
+    pub early_exit_fee: i128,         // Fee charged when a lessee returns before end_time
+    pub extension_pricing: ExtensionPricing, // Rate policy for extensions and renewals
+    pub claim_window: u64,            // Seconds after completion the owner may claim damages (0 = none)
+    pub claim_holdback: i128,         // Deposit held back from the refund during the claim window
+    pub penalty_policy: PenaltyPolicy, // Damage and late-return penalties, set at registration
+}
+
+// Damage charge the lessor may report on return without going to a dispute
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub enum DamagePenalty {
+    Percentage(u32),                  // Basis points of the security deposit
+    Fixed(i128),                      // Flat amount in the payment token
+}
+
+// Penalties applied when a lease is returned
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PenaltyPolicy {
+    pub damage: DamagePenalty,        // Most a lessor can charge for damages on return
+    pub late_fee_bps: u32,            // Late fee per started day, in basis points of the daily rate
+    pub grace_period: u64,            // Seconds after end_time before late fees start
+    pub cap: i128,                    // Most that late fees and damages can add up to
 }
 
 // Enum for mapping asset_id to Asset
@@ -53,23 +149,38 @@ pub enum AssetRegistry {
 
 // Lease data structure
 #[contracttype]
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Lease {
     pub lease_id: u64,                // Unique identifier for the lease
     pub asset_id: u64,                // Associated asset ID
     pub lessor: Address,              // Asset owner's address
     pub lessee: Address,              // Lessee's address
+    pub token: Address,               // Payment token copied from the asset
     pub start_time: u64,              // Lease start timestamp
     pub end_time: u64,                // Lease end timestamp
-    pub total_amount: i128,           // Total lease amount in XLM
-    pub security_deposit: i128,       // Security deposit amount in XLM
-    pub is_active: bool,              // Lease active status
-    pub is_completed: bool,           // Lease completion status
-    pub is_disputed: bool,            // Dispute status
-    pub last_payment: u64,            // Last payment timestamp
+    pub total_amount: i128,           // Total lease amount in the payment token
+    pub security_deposit: i128,       // Security deposit amount in the payment token
+    pub last_payment: u64,            // Last rent payment timestamp (the schedule runs from start_time)
     pub return_condition: String,     // Condition description upon return
     pub penalty_amount: i128,         // Penalty amount (if applicable)
     pub daily_rate: i128,             // Added daily_rate field which was missing
+    pub schedule: PaymentSchedule,    // How rent is paid over the lease
+    pub amount_paid: i128,            // Rent paid into escrow so far
+    pub installments_paid: u64,       // Number of rent installments paid
+    pub withdrawn: i128,              // Streamed rent already withdrawn by the lessor
+    pub early_exit_fee: i128,         // Early-exit fee copied from the asset
+    pub pending_extension_days: u64,  // Extra days requested by the lessee, awaiting approval
+    pub pending_extension_amount: i128, // Rent escrowed for the pending extension
+    pub auto_renew_days: u64,         // Renewal term in days (0 = auto-renew off)
+    pub status: LeaseStatus,          // Lifecycle status, changed only through transition_lease
+    pub claim_window: u64,            // Damage claim window copied from the asset
+    pub claim_holdback: i128,         // Deposit holdback copied from the asset
+    pub claim_deadline: u64,          // End of the damage claim window, set on completion
+    pub held_deposit: i128,           // Deposit still held for a possible damage claim
+    pub returned_at: u64,             // When the lessee handed the asset back (0 = not returned)
+    pub reported_damages: i128,       // Damages reported by the lessor on return
+    pub confirm_deadline: u64,        // When the pending return step auto-confirms
+    pub penalty_policy: PenaltyPolicy, // Penalty policy copied from the asset
 }
 
 // Enum for mapping lease_id to Lease
@@ -88,45 +199,656 @@ pub struct UserPortfolio {
     pub completed_leases: Vec<u64>,   // Completed lease IDs
 }
 
+// One page of assets; pass next_cursor back to continue (None = no more results)
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct AssetPage {
+    pub items: Vec<Asset>,
+    pub next_cursor: Option<u64>,
+}
+
+// One page of leases; pass next_cursor back to continue (None = no more results)
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct LeasePage {
+    pub items: Vec<Lease>,
+    pub next_cursor: Option<u64>,
+}
+
 // Enum for mapping user address to UserPortfolio
 #[contracttype]
 pub enum UserRegistry {
     User(Address)
 }
 
+// Rent payment schedule chosen when the lease is created
+#[contracttype]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PaymentSchedule {
+    Upfront,                          // Whole rent paid when the lease is created
+    Daily,                            // One installment per day
+    Weekly,                           // One installment per 7 days
+    Monthly,                          // One installment per 30 days
+    Stream,                           // Whole rent escrowed upfront, released per second
+}
+
+// Rate charged when a lease is extended or renewed
+#[contracttype]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ExtensionPricing {
+    Locked,                           // Keep the daily rate agreed when the lease was created
+    Current,                          // Use the asset's daily rate at the time of extension
+}
+
+// Access control roles
+#[contracttype]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Role {
+    Admin,                            // Manages roles; single holder, handed over in two steps
+    Arbitrator,                       // May stake into the pool that dispute panels are drawn from
+    Pauser,                           // Can freeze and unfreeze mutating entry points
+}
+
+// Enum for mapping (role, account) to membership
+#[contracttype]
+pub enum RoleRegistry {
+    Member(Role, Address)
+}
+
+// A piece of evidence attached to a dispute
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Evidence {
+    pub submitter: Address,           // Lessor or lessee who submitted it
+    pub content_hash: BytesN<32>,     // Hash of the off-chain content
+    pub uri: String,                  // Where the content can be fetched
+    pub submitted_at: u64,
+}
+
+// What a dispute is about
+#[contracttype]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DisputeKind {
+    Lease,                            // Filed by either party while the lease is live
+    DamageClaim,                      // Filed by the lessor against the held deposit after completion
+}
+
+// Dispute record, kept separately from the lease it concerns
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Dispute {
+    pub lease_id: u64,
+    pub kind: DisputeKind,
+    pub filer: Address,
+    pub reason: String,
+    pub filed_at: u64,
+    pub resolved_at: Option<u64>,     // Set once the panel's ruling is applied
+    pub evidence: Vec<Evidence>,      // Append-only, in submission order
+}
+
+// Part of a dispute award the lessee's escrow could not cover
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Debt {
+    pub lease_id: u64,
+    pub debtor: Address,              // Lessee
+    pub creditor: Address,            // Lessor
+    pub token: Address,
+    pub amount: i128,                 // Shortfall and unpaid rent recorded at resolution
+    pub repaid: i128,                 // Amount repaid so far
+}
+
+// Enum for mapping lease ID to its Dispute and any resulting Debt
+#[contracttype]
+pub enum DisputeRegistry {
+    Dispute(u64),
+    Debt(u64),
+}
+
+// Dispute arbitration settings (set by admin)
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArbitrationConfig {
+    pub stake_token: Address,         // Token arbitrators stake to join the pool
+    pub min_stake: i128,              // Stake needed to be drawn onto a panel
+    pub slash_amount: i128,           // Stake lost by a panelist who misses a deadline-resolved vote
+    pub panel_size: u32,              // Arbitrators drawn per dispute
+    pub quorum: u32,                  // Votes that resolve a dispute before its deadline
+    pub voting_period: u64,           // Seconds panelists have to vote
+}
+
+// A panelist's ruling on a dispute
+#[contracttype]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Vote {
+    pub in_favor_of_lessor: bool,
+    pub penalty_percentage: u64,      // Lessor award as a percentage of the deposit; may exceed 100
+}
+
+// Arbitrators assigned to a dispute and the votes cast so far
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct DisputePanel {
+    pub lease_id: u64,
+    pub arbitrators: Vec<Address>,
+    pub votes: Map<Address, Vote>,
+    pub quorum: u32,
+    pub slash_amount: i128,
+    pub deadline: u64,
+}
+
+// A pool member's stake and the number of unresolved panels they sit on
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArbitratorInfo {
+    pub stake: i128,
+    pub open_panels: u32,
+}
+
+// Enum for mapping arbitrators and disputes to their records
+#[contracttype]
+pub enum ArbitrationRegistry {
+    Arbitrator(Address),
+    Panel(u64),
+    Pool,
+}
+
+// Entry points that can be paused individually; settlement paths such as returns,
+// cancellations, refunds, withdrawals and dispute resolution are never paused
+#[contracttype]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Operation {
+    RegisterAsset,
+    UpdateAsset,                      // update_asset and the asset set_* calls
+    CreateLease,
+    ApproveLease,
+    RequestExtension,
+    ApproveExtension,
+    RenewLease,                       // renew_lease and set_auto_renew
+    JoinArbitratorPool,
+}
+
+// Emergency pause state
+#[contracttype]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PauseState {
+    pub all: bool,                    // Every pausable operation is frozen
+    pub operations: Vec<Operation>,   // Operations frozen individually
+}
+
 // Lease status enum
 #[contracttype]
-#[derive(Clone, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum LeaseStatus {
     Pending,
     Active,
+    Returned,                         // Handed back by the lessee, awaiting lessor confirmation
     Completed,
     Disputed,
     Canceled,
 }
 
+// Errors returned by the leasing contract, with stable numeric codes for clients
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum LeasingError {
+    AssetNotFound = 1,                // No asset stored under the given ID
+    LeaseNotFound = 2,                // No lease stored under the given ID
+    InvalidAssetParameters = 3,       // Rate, value, deposit or lease days out of range
+    AssetNotAvailable = 4,            // Asset is already leased or withdrawn
+    InvalidLeaseDuration = 5,         // Lease days outside the asset's min/max
+    NotAssetOwner = 6,                // Caller is not the asset owner / lessor
+    NotLessee = 7,                    // Caller is not the lessee
+    NotLeaseParty = 8,                // Caller is neither lessor nor lessee
+    InvalidPenaltyPercentage = 9,     // Penalty percentage above MAX_PENALTY_PERCENTAGE
+    LeaseClosed = 10,                 // Lease is completed or canceled
+    InstallmentsPaid = 11,            // All rent has already been paid
+    NotStreamed = 12,                 // Lease does not use the Stream schedule
+    LeaseNotActive = 13,              // Lease is not in the Active status
+    ExtensionPending = 14,            // An extension request is already pending
+    InvalidExtension = 15,            // Extension days zero or above max_lease_days
+    NoExtensionPending = 16,          // There is no extension request to act on
+    AutoRenewDisabled = 17,           // Auto-renew is not enabled for the lease
+    LeaseTermNotEnded = 18,           // Renewal attempted before end_time
+    InvalidFee = 19,                  // Fee is negative
+    InvalidStatusTransition = 20,     // Lease status cannot move to the requested status
+    AlreadyInitialized = 21,          // initialize has already been called
+    NotInitialized = 22,              // No admin has been set
+    Unauthorized = 23,                // Caller does not hold the required role
+    InvalidRole = 24,                 // Role cannot be granted or revoked directly
+    NoPendingAdmin = 25,              // No admin handover in progress for the caller
+    InvalidArbitrationConfig = 26,    // Settings out of range, or stake token change while stakes are held
+    ArbitrationNotConfigured = 27,    // configure_arbitration has not been called
+    InsufficientArbitrators = 28,     // Too few eligible arbitrators to draw a panel
+    InsufficientStake = 29,           // Stake amount must be positive
+    ArbitratorBusy = 30,              // Arbitrator still sits on an unresolved panel
+    DisputeNotFound = 31,             // No dispute was filed for the given lease
+    NotPanelMember = 32,              // Caller is not on the dispute's panel
+    AlreadyVoted = 33,                // Caller has already voted on the dispute
+    VotingClosed = 34,                // Voting deadline has passed
+    VotingOpen = 35,                  // Quorum not reached and deadline not yet passed
+    EvidenceLimitReached = 36,        // Dispute already holds MAX_EVIDENCE entries
+    NoDebtOutstanding = 37,           // No unpaid debt recorded for the lease
+    InvalidRepayment = 38,            // Repayment amount must be positive
+    ClaimWindowClosed = 39,           // No deposit is held or the claim window has ended
+    ClaimWindowOpen = 40,             // Held deposit cannot be released before the window ends
+    ClaimPending = 41,                // A damage claim on the held deposit is unresolved
+    LeaseNotReturned = 42,            // Lease is not awaiting return confirmation
+    DamagesAlreadyReported = 43,      // Lessor has already answered the return
+    InvalidDamages = 44,              // Damage amount is negative or above the penalty policy
+    ConfirmationPeriodOver = 45,      // Response came after the confirmation deadline
+    ConfirmationPending = 46,         // Confirmation deadline has not passed yet
+    NoDamagesReported = 47,           // There is no damage report to accept
+    ContractPaused = 48,              // Operation is paused
+    UnsupportedLayout = 49,           // Stored entry cannot be rewritten into the current layout
+    ArithmeticOverflow = 50,          // Amount or timestamp does not fit its type
+}
+
 #[contract]
 pub struct AssetLeasingContract;
 
 #[contractimpl]
 impl AssetLeasingContract {
+    // Set the contract admin (one-time setup)
+    pub fn initialize(env: Env, admin: Address) -> Result<(), LeasingError> {
+        if env.storage().instance().has(&ADMIN) {
+            return Err(LeasingError::AlreadyInitialized);
+        }
+        
+        // Authenticate admin
+        admin.require_auth();
+        
+        env.storage().instance().set(&ADMIN, &admin);
+        env.storage().instance().set(&SCHEMA_VER, &SCHEMA_VERSION);
+        env.events().publish((EVT_ROLE, symbol_short!("granted"), admin.clone()), Role::Admin);
+        
+        // Extend storage lifetime
+        env.storage().instance().extend_ttl(10000, 10000);
+        
+        log!(&env, "Contract initialized");
+        Ok(())
+    }
+
+    // Replace the contract code, keeping all storage; run migrate afterwards if the
+    // new code uses a newer storage layout (by admin)
+    pub fn upgrade(env: Env, admin: Address, new_wasm_hash: BytesN<32>) -> Result<bool, LeasingError> {
+        Self::require_admin(env.clone(), admin)?;
+        
+        env.deployer().update_current_contract_wasm(new_wasm_hash.clone());
+        env.events().publish((EVT_UPGRADE, symbol_short!("wasm")), new_wasm_hash);
+        
+        log!(&env, "Contract code upgraded");
+        Ok(true)
+    }
+
+    // Get the storage layout version; deployments that predate versioning report 1
+    pub fn get_schema_version(env: Env) -> u32 {
+        env.storage().instance().get(&SCHEMA_VER).unwrap_or(1)
+    }
+
+    // Get the contract admin
+    pub fn get_admin(env: Env) -> Result<Address, LeasingError> {
+        env.storage().instance().get(&ADMIN).ok_or(LeasingError::NotInitialized)
+    }
+
+    // Check whether an account holds a role
+    pub fn has_role(env: Env, role: Role, account: Address) -> bool {
+        match role {
+            Role::Admin => env.storage().instance().get::<_, Address>(&ADMIN) == Some(account),
+            _ => {
+                let key = RoleRegistry::Member(role, account);
+                if env.storage().persistent().has(&key) {
+                    env.storage().persistent().extend_ttl(&key, PERSISTENT_TTL_THRESHOLD, PERSISTENT_TTL_EXTEND);
+                    return true;
+                }
+                // Not yet moved by migrate
+                env.storage().instance().has(&key)
+            }
+        }
+    }
+
+    // Grant the Arbitrator or Pauser role (by admin)
+    pub fn grant_role(env: Env, admin: Address, role: Role, account: Address) -> Result<bool, LeasingError> {
+        Self::require_admin(env.clone(), admin)?;
+        
+        // Admin changes hands through transfer_admin / accept_admin
+        if role == Role::Admin {
+            return Err(LeasingError::InvalidRole);
+        }
+        
+        Self::save_role(env.clone(), role, account.clone());
+        env.events().publish((EVT_ROLE, symbol_short!("granted"), account), role);
+        
+        // Extend storage lifetime
+        env.storage().instance().extend_ttl(10000, 10000);
+        
+        Ok(true)
+    }
+
+    // Revoke the Arbitrator or Pauser role (by admin)
+    pub fn revoke_role(env: Env, admin: Address, role: Role, account: Address) -> Result<bool, LeasingError> {
+        Self::require_admin(env.clone(), admin)?;
+        
+        if role == Role::Admin {
+            return Err(LeasingError::InvalidRole);
+        }
+        
+        let key = RoleRegistry::Member(role, account.clone());
+        env.storage().persistent().remove(&key);
+        env.storage().instance().remove(&key);
+        env.events().publish((EVT_ROLE, symbol_short!("revoked"), account), role);
+        
+        // Extend storage lifetime
+        env.storage().instance().extend_ttl(10000, 10000);
+        
+        Ok(true)
+    }
+
+    // Propose a new admin; takes effect once they call accept_admin (by admin)
+    pub fn transfer_admin(env: Env, admin: Address, new_admin: Address) -> Result<bool, LeasingError> {
+        Self::require_admin(env.clone(), admin)?;
+        
+        env.storage().instance().set(&PEND_ADMN, &new_admin);
+        env.events().publish((EVT_ROLE, symbol_short!("proposed"), new_admin), Role::Admin);
+        
+        // Extend storage lifetime
+        env.storage().instance().extend_ttl(10000, 10000);
+        
+        Ok(true)
+    }
+
+    // Accept a pending admin handover (by the proposed admin)
+    pub fn accept_admin(env: Env, new_admin: Address) -> Result<bool, LeasingError> {
+        // Authenticate new admin
+        new_admin.require_auth();
+        
+        let pending: Option<Address> = env.storage().instance().get(&PEND_ADMN);
+        if pending != Some(new_admin.clone()) {
+            return Err(LeasingError::NoPendingAdmin);
+        }
+        
+        let old_admin = Self::get_admin(env.clone())?;
+        env.storage().instance().set(&ADMIN, &new_admin);
+        env.storage().instance().remove(&PEND_ADMN);
+        env.events().publish((EVT_ROLE, symbol_short!("revoked"), old_admin), Role::Admin);
+        env.events().publish((EVT_ROLE, symbol_short!("granted"), new_admin), Role::Admin);
+        
+        // Extend storage lifetime
+        env.storage().instance().extend_ttl(10000, 10000);
+        
+        log!(&env, "Admin handover accepted");
+        Ok(true)
+    }
+
+    // Freeze every pausable operation (by a pauser or the admin)
+    pub fn pause(env: Env, pauser: Address) -> Result<bool, LeasingError> {
+        Self::require_pauser(env.clone(), pauser)?;
+        
+        let mut state = Self::get_pause_state(env.clone());
+        state.all = true;
+        Self::save_pause_state(env.clone(), &state);
+        
+        log!(&env, "Contract paused");
+        Ok(true)
+    }
+
+    // Lift the global freeze; individually paused operations stay paused (by a pauser or the admin)
+    pub fn unpause(env: Env, pauser: Address) -> Result<bool, LeasingError> {
+        Self::require_pauser(env.clone(), pauser)?;
+        
+        let mut state = Self::get_pause_state(env.clone());
+        state.all = false;
+        Self::save_pause_state(env.clone(), &state);
+        
+        log!(&env, "Contract unpaused");
+        Ok(true)
+    }
+
+    // Freeze a single operation (by a pauser or the admin)
+    pub fn pause_operation(env: Env, pauser: Address, operation: Operation) -> Result<bool, LeasingError> {
+        Self::require_pauser(env.clone(), pauser)?;
+        
+        let mut state = Self::get_pause_state(env.clone());
+        if !state.operations.contains(operation) {
+            state.operations.push_back(operation);
+            Self::save_pause_state(env.clone(), &state);
+        }
+        
+        Ok(true)
+    }
+
+    // Unfreeze a single operation (by a pauser or the admin)
+    pub fn unpause_operation(env: Env, pauser: Address, operation: Operation) -> Result<bool, LeasingError> {
+        Self::require_pauser(env.clone(), pauser)?;
+        
+        let mut state = Self::get_pause_state(env.clone());
+        if let Some(index) = state.operations.first_index_of(operation) {
+            state.operations.remove(index);
+            Self::save_pause_state(env.clone(), &state);
+        }
+        
+        Ok(true)
+    }
+
+    // Get the current pause state
+    pub fn get_pause_state(env: Env) -> PauseState {
+        env.storage().instance().get(&PAUSED).unwrap_or(PauseState {
+            all: false,
+            operations: Vec::new(&env),
+        })
+    }
+
+    // Check whether an operation is currently paused
+    pub fn is_paused(env: Env, operation: Operation) -> bool {
+        let state = Self::get_pause_state(env);
+        state.all || state.operations.contains(operation)
+    }
+
+    // Set how dispute panels are staked, drawn and resolved (by admin)
+    pub fn configure_arbitration(env: Env, admin: Address, config: ArbitrationConfig) -> Result<bool, LeasingError> {
+        Self::require_admin(env.clone(), admin)?;
+        
+        if config.panel_size == 0
+            || config.quorum == 0
+            || config.quorum > config.panel_size
+            || config.voting_period == 0
+            || config.slash_amount < 0
+            || config.min_stake < config.slash_amount
+        {
+            return Err(LeasingError::InvalidArbitrationConfig);
+        }
+        
+        // Stakes are refunded and slashed in the configured token, so it stays put
+        // until every stake is withdrawn and every panel has ruled
+        if let Ok(current) = Self::get_arbitration_config(env.clone()) {
+            let total_stake: i128 = env.storage().instance().get(&ARB_STAKE).unwrap_or(0);
+            let open_panels = Self::get_asset_stats(env.clone()).disputed_leases;
+            let staked = total_stake > 0 || !Self::get_arbitrator_pool(env.clone()).is_empty();
+            if current.stake_token != config.stake_token && (staked || open_panels > 0) {
+                return Err(LeasingError::InvalidArbitrationConfig);
+            }
+        }
+        
+        env.storage().instance().set(&ARB_CFG, &config);
+        
+        // Extend storage lifetime
+        env.storage().instance().extend_ttl(10000, 10000);
+        
+        log!(&env, "Arbitration configured: panel of {}, quorum {}", config.panel_size, config.quorum);
+        Ok(true)
+    }
+
+    // Get the arbitration settings
+    pub fn get_arbitration_config(env: Env) -> Result<ArbitrationConfig, LeasingError> {
+        env.storage().instance().get(&ARB_CFG).ok_or(LeasingError::ArbitrationNotConfigured)
+    }
+
+    // Stake into the arbitrator pool, or top up an existing stake (by an arbitrator)
+    pub fn join_arbitrator_pool(env: Env, arbitrator: Address, amount: i128) -> Result<i128, LeasingError> {
+        // Refuse while paused
+        Self::require_not_paused(env.clone(), Operation::JoinArbitratorPool)?;
+        
+        // Authenticate arbitrator and check their role
+        arbitrator.require_auth();
+        if !Self::has_role(env.clone(), Role::Arbitrator, arbitrator.clone()) {
+            return Err(LeasingError::Unauthorized);
+        }
+        
+        if amount <= 0 {
+            return Err(LeasingError::InsufficientStake);
+        }
+        
+        let config = Self::get_arbitration_config(env.clone())?;
+        token::Client::new(&env, &config.stake_token)
+            .transfer(&arbitrator, &env.current_contract_address(), &amount);
+        
+        let mut info = Self::get_arbitrator(env.clone(), arbitrator.clone());
+        info.stake = Self::add_amount(info.stake, amount)?;
+        Self::save_arbitrator(env.clone(), arbitrator.clone(), &info);
+        Self::add_total_stake(env.clone(), amount)?;
+        
+        // Only sufficiently staked arbitrators are listed in the pool
+        let mut pool = Self::get_arbitrator_pool(env.clone());
+        if info.stake >= config.min_stake && !pool.contains(&arbitrator) {
+            pool.push_back(arbitrator.clone());
+            Self::save_pool(env.clone(), &pool);
+        }
+        
+        // Extend storage lifetime
+        env.storage().instance().extend_ttl(10000, 10000);
+        
+        env.events().publish((EVT_ARBITER, symbol_short!("staked"), arbitrator), info.stake);
+        
+        Ok(info.stake)
+    }
+
+    // Leave the arbitrator pool and withdraw the stake (by an arbitrator with no open panels)
+    pub fn leave_arbitrator_pool(env: Env, arbitrator: Address) -> Result<i128, LeasingError> {
+        // Authenticate arbitrator
+        arbitrator.require_auth();
+        
+        let info = Self::get_arbitrator(env.clone(), arbitrator.clone());
+        if info.open_panels > 0 {
+            return Err(LeasingError::ArbitratorBusy);
+        }
+        
+        let mut pool = Self::get_arbitrator_pool(env.clone());
+        if let Some(index) = pool.first_index_of(&arbitrator) {
+            pool.remove(index);
+            Self::save_pool(env.clone(), &pool);
+        }
+        
+        if info.stake > 0 {
+            let config = Self::get_arbitration_config(env.clone())?;
+            token::Client::new(&env, &config.stake_token)
+                .transfer(&env.current_contract_address(), &arbitrator, &info.stake);
+            Self::add_total_stake(env.clone(), -info.stake)?;
+        }
+        env.storage().persistent().remove(&ArbitrationRegistry::Arbitrator(arbitrator.clone()));
+        
+        // Extend storage lifetime
+        env.storage().instance().extend_ttl(10000, 10000);
+        
+        env.events().publish((EVT_ARBITER, symbol_short!("left"), arbitrator), info.stake);
+        
+        Ok(info.stake)
+    }
+
+    // Get an arbitrator's stake and open panel count
+    pub fn get_arbitrator(env: Env, arbitrator: Address) -> ArbitratorInfo {
+        env.storage().persistent()
+            .get(&ArbitrationRegistry::Arbitrator(arbitrator))
+            .unwrap_or(ArbitratorInfo { stake: 0, open_panels: 0 })
+    }
+
+    // Get the arbitrators currently staked into the pool
+    pub fn get_arbitrator_pool(env: Env) -> Vec<Address> {
+        let key = ArbitrationRegistry::Pool;
+        if let Some(pool) = env.storage().persistent().get(&key) {
+            env.storage().persistent().extend_ttl(&key, PERSISTENT_TTL_THRESHOLD, PERSISTENT_TTL_EXTEND);
+            return pool;
+        }
+        // Not yet moved by migrate
+        env.storage().instance().get(&ARB_POOL).unwrap_or(Vec::new(&env))
+    }
+
+    // Repay part or all of a dispute debt directly to the lessor (by lessee or a sponsor)
+    pub fn repay_debt(env: Env, lease_id: u64, payer: Address, amount: i128) -> Result<i128, LeasingError> {
+        // Authenticate payer
+        payer.require_auth();
+        
+        if amount <= 0 {
+            return Err(LeasingError::InvalidRepayment);
+        }
+        
+        let mut debt = Self::get_debt(env.clone(), lease_id)?;
+        let outstanding = Self::sub_amount(debt.amount, debt.repaid)?;
+        if outstanding <= 0 {
+            return Err(LeasingError::NoDebtOutstanding);
+        }
+        
+        // Never take more than is owed
+        let payment = amount.min(outstanding);
+        token::Client::new(&env, &debt.token).transfer(&payer, &debt.creditor, &payment);
+        debt.repaid = Self::add_amount(debt.repaid, payment)?;
+        Self::save_debt(env.clone(), &debt);
+        
+        // Update stats
+        let mut stats = Self::get_asset_stats(env.clone());
+        Self::add_token_amount(&mut stats.total_earnings, &debt.token, payment)?;
+        Self::save_stats(env.clone(), &stats);
+        
+        // Extend storage lifetime
+        env.storage().instance().extend_ttl(10000, 10000);
+        
+        env.events().publish((EVT_DISPUTE, symbol_short!("debt"), lease_id), debt.clone());
+        
+        log!(&env, "Debt repayment of {} for lease {}", payment, lease_id);
+        Self::sub_amount(debt.amount, debt.repaid)
+    }
+
+    // Get the debt recorded against a lease's lessee
+    pub fn get_debt(env: Env, lease_id: u64) -> Result<Debt, LeasingError> {
+        env.storage().persistent()
+            .get(&DisputeRegistry::Debt(lease_id))
+            .ok_or(LeasingError::NoDebtOutstanding)
+    }
+
+    // Get the dispute filed against a lease
+    pub fn get_dispute(env: Env, lease_id: u64) -> Result<Dispute, LeasingError> {
+        env.storage().persistent()
+            .get(&DisputeRegistry::Dispute(lease_id))
+            .ok_or(LeasingError::DisputeNotFound)
+    }
+
+    // Get the panel assigned to a disputed lease
+    pub fn get_dispute_panel(env: Env, lease_id: u64) -> Result<DisputePanel, LeasingError> {
+        env.storage().persistent()
+            .get(&ArbitrationRegistry::Panel(lease_id))
+            .ok_or(LeasingError::DisputeNotFound)
+    }
+
     // Register a new asset
     pub fn register_asset(
         env: Env,
         owner: Address,
         title: String,
         description: String,
+        token: Address,
         asset_value: i128,
         daily_rate: i128,
         min_lease_days: u64,
         max_lease_days: u64,
         security_deposit: i128,
-    ) -> u64 {
+        penalty_policy: PenaltyPolicy,
+    ) -> Result<u64, LeasingError> {
+        // Refuse while paused
+        Self::require_not_paused(env.clone(), Operation::RegisterAsset)?;
+        
         // Verify inputs
-        if daily_rate <= 0 || asset_value <= 0 || security_deposit < 0 {
-            log!(&env, "Invalid asset parameters");
-            panic!("Invalid asset parameters");
-        }
+        Self::validate_asset_terms(asset_value, daily_rate, min_lease_days, max_lease_days, security_deposit)?;
+        Self::validate_penalty_policy(&penalty_policy)?;
 
         // Authenticate owner
         owner.require_auth();
@@ -141,6 +863,7 @@ impl AssetLeasingContract {
             owner: owner.clone(),
             title,
             description,
+            token,
             asset_value,
             daily_rate,
             available: true,
@@ -148,15 +871,20 @@ impl AssetLeasingContract {
             max_lease_days,
             security_deposit,
             created_at: env.ledger().timestamp(),
+            early_exit_fee: 0,
+            claim_window: 0,
+            claim_holdback: 0,
+            extension_pricing: ExtensionPricing::Locked,
+            penalty_policy,
         };
 
         // Store asset
-        env.storage().instance().set(&AssetRegistry::Asset(asset_count), &asset);
+        Self::save_asset(env.clone(), &asset);
         
         // Update stats
         let mut stats = Self::get_asset_stats(env.clone());
         stats.total_assets += 1;
-        env.storage().instance().set(&ASSET_STS, &stats);
+        Self::save_stats(env.clone(), &stats);
         
         // Update asset count
         env.storage().instance().set(&ASSET_CNT, &asset_count);
@@ -164,13 +892,15 @@ impl AssetLeasingContract {
         // Update user portfolio
         let mut portfolio = Self::get_user_portfolio(env.clone(), owner.clone());
         portfolio.owned_assets.push_back(asset_count);
-        env.storage().instance().set(&UserRegistry::User(owner), &portfolio);
+        Self::save_portfolio(env.clone(), owner, &portfolio);
         
         // Extend storage lifetime
         env.storage().instance().extend_ttl(10000, 10000);
         
+        Self::publish_asset(env.clone(), symbol_short!("created"), &asset);
+        
         log!(&env, "Asset registered with ID: {}", asset_count);
-        asset_count
+        Ok(asset_count)
     }
 
     // Create a lease proposal (by lessee)
@@ -179,30 +909,30 @@ impl AssetLeasingContract {
         asset_id: u64,
         lessee: Address,
         days: u64,
-    ) -> u64 {
+        schedule: PaymentSchedule,
+    ) -> Result<u64, LeasingError> {
+        // Refuse while paused
+        Self::require_not_paused(env.clone(), Operation::CreateLease)?;
+        
         // Authenticate lessee
         lessee.require_auth();
         
         // Get asset
-        let asset = Self::get_asset(env.clone(), asset_id);
+        let asset = Self::get_asset(env.clone(), asset_id)?;
         
         // Check availability
         if !asset.available {
-            log!(&env, "Asset is not available for lease");
-            panic!("Asset is not available for lease");
+            return Err(LeasingError::AssetNotAvailable);
         }
         
         // Validate lease period
-        if days < asset.min_lease_days || days > asset.max_lease_days {
-            log!(&env, "Lease duration outside acceptable range");
-            panic!("Lease duration outside acceptable range");
+        if days == 0 || days < asset.min_lease_days || days > asset.max_lease_days {
+            return Err(LeasingError::InvalidLeaseDuration);
         }
         
         // Calculate total amount
-        let total_amount = asset.daily_rate * days as i128;
+        let total_amount = Self::lease_amount(asset.daily_rate, days)?;
         let security_deposit = asset.security_deposit;
-        // Renamed to avoid unused variable warning
-        let _total_required = total_amount + security_deposit;
         
         // Get new lease_id
         let mut lease_count: u64 = env.storage().instance().get(&LEASE_CNT).unwrap_or(0);
@@ -210,34 +940,60 @@ impl AssetLeasingContract {
         
         // Get current time
         let current_time = env.ledger().timestamp();
-        let end_time = current_time + (days * 24 * 60 * 60); // Convert days to seconds
+        let end_time = Self::add_days(current_time, days)?;
+        
+        // Late fees start after the grace period, which must not overflow either
+        Self::add_seconds(end_time, asset.penalty_policy.grace_period)?;
         
         // Create lease
-        let lease = Lease {
+        let mut lease = Lease {
             lease_id: lease_count,
             asset_id,
             lessor: asset.owner.clone(),
             lessee: lessee.clone(),
+            token: asset.token.clone(),
             start_time: current_time,
             end_time,
             total_amount,
             security_deposit,
-            is_active: false, // Requires approval
-            is_completed: false,
-            is_disputed: false,
-            last_payment: 0,
+            last_payment: current_time,
             return_condition: String::from_str(&env, ""),
             penalty_amount: 0,
             daily_rate: asset.daily_rate, // Added daily_rate field
+            schedule,
+            amount_paid: 0,
+            installments_paid: 0,
+            withdrawn: 0,
+            early_exit_fee: asset.early_exit_fee,
+            pending_extension_days: 0,
+            pending_extension_amount: 0,
+            auto_renew_days: 0,
+            status: LeaseStatus::Pending, // Requires approval
+            claim_window: asset.claim_window,
+            claim_holdback: asset.claim_holdback,
+            claim_deadline: 0,
+            held_deposit: 0,
+            returned_at: 0,
+            reported_damages: 0,
+            confirm_deadline: 0,
+            penalty_policy: asset.penalty_policy.clone(),
         };
         
+        // Move the deposit and first installment from lessee into contract escrow
+        let first_installment = Self::installment_amount(&lease)?;
+        let escrow = security_deposit.checked_add(first_installment).ok_or(LeasingError::ArithmeticOverflow)?;
+        let token_client = token::Client::new(&env, &asset.token);
+        token_client.transfer(&lessee, &env.current_contract_address(), &escrow);
+        lease.amount_paid = first_installment;
+        lease.installments_paid = 1;
+        
         // Store lease
-        env.storage().instance().set(&LeaseRegistry::Lease(lease_count), &lease);
+        Self::save_lease(env.clone(), &lease);
         
         // Update asset availability
         let mut updated_asset = asset.clone();
         updated_asset.available = false;
-        env.storage().instance().set(&AssetRegistry::Asset(asset_id), &updated_asset);
+        Self::save_asset(env.clone(), &updated_asset);
         
         // Update lease count
         env.storage().instance().set(&LEASE_CNT, &lease_count);
@@ -245,128 +1001,245 @@ impl AssetLeasingContract {
         // Update lessee portfolio
         let mut lessee_portfolio = Self::get_user_portfolio(env.clone(), lessee.clone());
         lessee_portfolio.active_leases_as_lessee.push_back(lease_count);
-        env.storage().instance().set(&UserRegistry::User(lessee), &lessee_portfolio);
+        Self::save_portfolio(env.clone(), lessee, &lessee_portfolio);
         
         // Update lessor portfolio
         let mut lessor_portfolio = Self::get_user_portfolio(env.clone(), asset.owner.clone());
         lessor_portfolio.active_leases_as_lessor.push_back(lease_count);
-        env.storage().instance().set(&UserRegistry::User(asset.owner), &lessor_portfolio);
+        Self::save_portfolio(env.clone(), asset.owner, &lessor_portfolio);
         
         // Extend storage lifetime
         env.storage().instance().extend_ttl(10000, 10000);
         
+        Self::publish_lease(env.clone(), EVT_LEASE, symbol_short!("created"), &lease);
+        
         log!(&env, "Lease created with ID: {}", lease_count);
-        lease_count
+        Ok(lease_count)
     }
 
     // Approve and activate lease (by lessor/owner)
-    pub fn approve_lease(env: Env, lease_id: u64, lessor: Address) -> bool {
+    pub fn approve_lease(env: Env, lease_id: u64, lessor: Address) -> Result<bool, LeasingError> {
+        // Refuse while paused
+        Self::require_not_paused(env.clone(), Operation::ApproveLease)?;
+        
         // Authenticate lessor
         lessor.require_auth();
         
         // Get lease
-        let mut lease = Self::get_lease(env.clone(), lease_id);
+        let mut lease = Self::get_lease(env.clone(), lease_id)?;
         
         // Verify lessor is the asset owner
         if lease.lessor != lessor {
-            log!(&env, "Only the asset owner can approve this lease");
-            panic!("Only the asset owner can approve this lease");
+            return Err(LeasingError::NotAssetOwner);
         }
         
-        // Verify lease isn't already active
-        if lease.is_active {
-            log!(&env, "Lease is already active");
-            panic!("Lease is already active");
-        }
+        // The term runs from approval, not from the proposal
+        let current_time = env.ledger().timestamp();
+        let end_time = Self::add_days(current_time, Self::lease_days(&lease))?;
+        Self::add_seconds(end_time, lease.penalty_policy.grace_period)?;
+        lease.start_time = current_time;
+        lease.end_time = end_time;
+        lease.last_payment = current_time;
         
         // Activate lease
-        lease.is_active = true;
-        lease.last_payment = env.ledger().timestamp();
-        env.storage().instance().set(&LeaseRegistry::Lease(lease_id), &lease);
+        Self::transition_lease(env.clone(), &mut lease, LeaseStatus::Active)?;
+        Self::save_lease(env.clone(), &lease);
         
-        // Update stats
+        // Update stats - rent paid so far and deposit were escrowed in create_lease
         let mut stats = Self::get_asset_stats(env.clone());
         stats.active_leases += 1;
-        stats.total_value_locked += lease.total_amount + lease.security_deposit;
-        env.storage().instance().set(&ASSET_STS, &stats);
+        let escrowed = Self::add_amount(lease.amount_paid, lease.security_deposit)?;
+        Self::add_token_amount(&mut stats.total_value_locked, &lease.token, escrowed)?;
+        Self::save_stats(env.clone(), &stats);
         
         // Extend storage lifetime
         env.storage().instance().extend_ttl(10000, 10000);
         
+        Self::publish_lease(env.clone(), EVT_LEASE, symbol_short!("approved"), &lease);
+        
         log!(&env, "Lease {} approved and activated", lease_id);
-        true
+        Ok(true)
+    }
+
+    // Reject a pending lease proposal and refund the lessee (by lessor/owner)
+    pub fn reject_lease(env: Env, lease_id: u64, lessor: Address) -> Result<bool, LeasingError> {
+        // Authenticate lessor
+        lessor.require_auth();
+        
+        // Get lease
+        let lease = Self::get_lease(env.clone(), lease_id)?;
+        
+        // Verify lessor is the asset owner
+        if lease.lessor != lessor {
+            return Err(LeasingError::NotAssetOwner);
+        }
+        
+        let lease = Self::cancel_pending_lease(env.clone(), lease)?;
+        Self::publish_lease(env.clone(), EVT_LEASE, symbol_short!("rejected"), &lease);
+        
+        log!(&env, "Lease {} rejected", lease_id);
+        Ok(true)
     }
 
-    // Complete a lease (return asset)
-    pub fn complete_lease(
-        env: Env, 
-        lease_id: u64, 
-        lessee: Address, 
-        return_condition: String,
-        has_damages: bool
-    ) -> bool {
+    // Withdraw a pending lease proposal and get a refund (by lessee)
+    pub fn cancel_lease(env: Env, lease_id: u64, lessee: Address) -> Result<bool, LeasingError> {
         // Authenticate lessee
         lessee.require_auth();
         
         // Get lease
-        let mut lease = Self::get_lease(env.clone(), lease_id);
+        let lease = Self::get_lease(env.clone(), lease_id)?;
         
         // Verify lessee
         if lease.lessee != lessee {
-            log!(&env, "Only the lessee can complete this lease");
-            panic!("Only the lessee can complete this lease");
-        }
-        
-        // Verify lease is active
-        if !lease.is_active || lease.is_completed {
-            log!(&env, "Lease is not active or already completed");
-            panic!("Lease is not active or already completed");
+            return Err(LeasingError::NotLessee);
         }
         
-        // Update lease status
-        lease.is_active = false;
-        lease.is_completed = true;
-        lease.return_condition = return_condition;
+        let lease = Self::cancel_pending_lease(env.clone(), lease)?;
+        Self::publish_lease(env.clone(), EVT_LEASE, symbol_short!("canceled"), &lease);
         
-        // Calculate penalties for damages or late return
-        let current_time = env.ledger().timestamp();
-        let penalty = if has_damages {
-            // Apply damage penalty (25% of security deposit)
-            lease.security_deposit / 4
-        } else if current_time > lease.end_time {
-            // Apply late return penalty (10% of daily rate per day)
-            let days_late = (current_time - lease.end_time) / (24 * 60 * 60);
-            lease.daily_rate / 10 * days_late as i128
-        } else {
-            0
-        };
+        log!(&env, "Lease {} canceled", lease_id);
+        Ok(true)
+    }
+
+    // Hand the asset back with condition notes; the lessor then confirms (by lessee)
+    pub fn return_asset(env: Env, lease_id: u64, lessee: Address, return_condition: String) -> Result<bool, LeasingError> {
+        // Authenticate lessee
+        lessee.require_auth();
         
-        lease.penalty_amount = penalty;
+        // Get lease
+        let mut lease = Self::get_lease(env.clone(), lease_id)?;
+        
+        // Verify lessee
+        if lease.lessee != lessee {
+            return Err(LeasingError::NotLessee);
+        }
+        
+        // Update lease status
+        Self::transition_lease(env.clone(), &mut lease, LeaseStatus::Returned)?;
+        lease.return_condition = return_condition;
+        
+        let current_time = env.ledger().timestamp();
+        lease.returned_at = current_time;
+        lease.confirm_deadline = Self::add_seconds(current_time, RETURN_CONFIRM_PERIOD)?;
+        
+        // Collect any rent still outstanding for the time used while the lessee is signing
+        let rent = Self::rent_due(&lease, current_time)?;
+        Self::collect_outstanding_rent(env.clone(), &mut lease, rent)?;
         
         // Store updated lease
-        env.storage().instance().set(&LeaseRegistry::Lease(lease_id), &lease);
+        Self::save_lease(env.clone(), &lease);
         
-        // Make asset available again
-        let mut asset = Self::get_asset(env.clone(), lease.asset_id);
-        asset.available = true;
-        env.storage().instance().set(&AssetRegistry::Asset(lease.asset_id), &asset);
+        // Extend storage lifetime
+        env.storage().instance().extend_ttl(10000, 10000);
         
-        // Update stats
-        let mut stats = Self::get_asset_stats(env.clone());
-        stats.active_leases -= 1;
-        stats.completed_leases += 1;
-        stats.total_value_locked -= lease.total_amount + lease.security_deposit;
-        stats.total_earnings += lease.total_amount + penalty;
-        env.storage().instance().set(&ASSET_STS, &stats);
+        Self::publish_lease(env.clone(), EVT_LEASE, symbol_short!("returned"), &lease);
         
-        // Update user portfolios
-        Self::update_portfolios_on_completion(env.clone(), lease_id, lease.lessee.clone(), lease.lessor.clone());
+        log!(&env, "Asset returned for lease {}", lease_id);
+        Ok(true)
+    }
+
+    // Accept a return, or report damages for the lessee to accept or dispute (by lessor)
+    pub fn confirm_return(env: Env, lease_id: u64, lessor: Address, damages: i128) -> Result<bool, LeasingError> {
+        // Authenticate lessor
+        lessor.require_auth();
+        
+        // Get lease
+        let mut lease = Self::get_lease(env.clone(), lease_id)?;
+        
+        // Verify lessor
+        if lease.lessor != lessor {
+            return Err(LeasingError::NotAssetOwner);
+        }
+        
+        if lease.status != LeaseStatus::Returned {
+            return Err(LeasingError::LeaseNotReturned);
+        }
+        if lease.reported_damages > 0 {
+            return Err(LeasingError::DamagesAlreadyReported);
+        }
+        // Larger damage claims go through a dispute or damage claim instead
+        if damages < 0 || damages > Self::damage_limit(&lease)? {
+            return Err(LeasingError::InvalidDamages);
+        }
+        if env.ledger().timestamp() > lease.confirm_deadline {
+            return Err(LeasingError::ConfirmationPeriodOver);
+        }
+        
+        if damages == 0 {
+            Self::finish_return(env.clone(), lease, 0)?;
+        } else {
+            // The lessee now has a confirmation period to accept or dispute the report
+            lease.reported_damages = damages;
+            lease.confirm_deadline = Self::add_seconds(env.ledger().timestamp(), RETURN_CONFIRM_PERIOD)?;
+            Self::save_lease(env.clone(), &lease);
+            Self::publish_lease(env.clone(), EVT_LEASE, symbol_short!("damaged"), &lease);
+        }
+        
+        // Extend storage lifetime
+        env.storage().instance().extend_ttl(10000, 10000);
+        
+        log!(&env, "Return confirmed for lease {}, damages reported: {}", lease_id, damages);
+        Ok(true)
+    }
+
+    // Preview the penalty a return at the given time would carry, with the given damages
+    pub fn preview_penalty(env: Env, lease_id: u64, return_time: u64, damages: i128) -> Result<i128, LeasingError> {
+        let lease = Self::get_lease(env.clone(), lease_id)?;
+        if damages < 0 || damages > Self::damage_limit(&lease)? {
+            return Err(LeasingError::InvalidDamages);
+        }
+        Self::return_penalty(&lease, return_time, damages)
+    }
+
+    // Accept the lessor's damage report and settle the lease (by lessee)
+    pub fn accept_damages(env: Env, lease_id: u64, lessee: Address) -> Result<bool, LeasingError> {
+        // Authenticate lessee
+        lessee.require_auth();
+        
+        // Get lease
+        let lease = Self::get_lease(env.clone(), lease_id)?;
+        
+        // Verify lessee
+        if lease.lessee != lessee {
+            return Err(LeasingError::NotLessee);
+        }
+        
+        if lease.status != LeaseStatus::Returned {
+            return Err(LeasingError::LeaseNotReturned);
+        }
+        if lease.reported_damages == 0 {
+            return Err(LeasingError::NoDamagesReported);
+        }
+        
+        let damages = lease.reported_damages;
+        Self::finish_return(env.clone(), lease, damages)?;
+        
+        // Extend storage lifetime
+        env.storage().instance().extend_ttl(10000, 10000);
+        
+        Ok(true)
+    }
+
+    // Settle a return whose confirmation deadline passed without a response (callable by anyone).
+    // A return the lessor never answered settles without damages; an unanswered
+    // damage report settles as if the lessee accepted it.
+    pub fn finalize_return(env: Env, lease_id: u64) -> Result<bool, LeasingError> {
+        let lease = Self::get_lease(env.clone(), lease_id)?;
+        
+        if lease.status != LeaseStatus::Returned {
+            return Err(LeasingError::LeaseNotReturned);
+        }
+        if env.ledger().timestamp() <= lease.confirm_deadline {
+            return Err(LeasingError::ConfirmationPending);
+        }
+        
+        let damages = lease.reported_damages;
+        Self::finish_return(env.clone(), lease, damages)?;
         
         // Extend storage lifetime
         env.storage().instance().extend_ttl(10000, 10000);
         
-        log!(&env, "Lease {} completed, penalty: {}", lease_id, penalty);
-        true
+        Ok(true)
     }
 
     // File a dispute for a lease
@@ -375,185 +1248,1488 @@ impl AssetLeasingContract {
         lease_id: u64,
         filer: Address,
         dispute_reason: String
-    ) -> bool {
+    ) -> Result<bool, LeasingError> {
         // Authenticate filer
         filer.require_auth();
         
-        // Get lease
-        let mut lease = Self::get_lease(env.clone(), lease_id);
+        // Get lease
+        let mut lease = Self::get_lease(env.clone(), lease_id)?;
+        
+        // Verify filer is lessor or lessee
+        if lease.lessor != filer && lease.lessee != filer {
+            return Err(LeasingError::NotLeaseParty);
+        }
+        
+        // Mark as disputed
+        Self::transition_lease(env.clone(), &mut lease, LeaseStatus::Disputed)?;
+        
+        // Open the dispute record
+        let dispute = Dispute {
+            lease_id,
+            kind: DisputeKind::Lease,
+            filer,
+            reason: dispute_reason,
+            filed_at: env.ledger().timestamp(),
+            resolved_at: None,
+            evidence: Vec::new(&env),
+        };
+        Self::save_dispute(env.clone(), &dispute);
+        
+        // Draw the arbitrator panel that will rule on the dispute
+        Self::assign_panel(env.clone(), &lease)?;
+        
+        // Store updated lease
+        Self::save_lease(env.clone(), &lease);
+        
+        // Update stats
+        let mut stats = Self::get_asset_stats(env.clone());
+        stats.disputed_leases += 1;
+        Self::save_stats(env.clone(), &stats);
+        
+        // Extend storage lifetime
+        env.storage().instance().extend_ttl(10000, 10000);
+        
+        Self::publish_lease(env.clone(), EVT_DISPUTE, symbol_short!("filed"), &lease);
+        
+        log!(&env, "Dispute filed for lease {}", lease_id);
+        Ok(true)
+    }
+
+    // Claim damages against the held deposit of a completed lease (by lessor, within the claim window)
+    pub fn file_damage_claim(env: Env, lease_id: u64, lessor: Address, reason: String) -> Result<bool, LeasingError> {
+        // Authenticate lessor
+        lessor.require_auth();
+        
+        // Get lease
+        let lease = Self::get_lease(env.clone(), lease_id)?;
+        
+        // Verify lessor
+        if lease.lessor != lessor {
+            return Err(LeasingError::NotAssetOwner);
+        }
+        
+        if let Ok(dispute) = Self::get_dispute(env.clone(), lease_id) {
+            if dispute.resolved_at.is_none() {
+                return Err(LeasingError::ClaimPending);
+            }
+        }
+        if lease.status != LeaseStatus::Completed
+            || lease.held_deposit == 0
+            || env.ledger().timestamp() > lease.claim_deadline
+        {
+            return Err(LeasingError::ClaimWindowClosed);
+        }
+        
+        // Open the claim and draw the panel that will rule on it
+        let dispute = Dispute {
+            lease_id,
+            kind: DisputeKind::DamageClaim,
+            filer: lessor,
+            reason,
+            filed_at: env.ledger().timestamp(),
+            resolved_at: None,
+            evidence: Vec::new(&env),
+        };
+        Self::save_dispute(env.clone(), &dispute);
+        Self::assign_panel(env.clone(), &lease)?;
+        
+        // Update stats
+        let mut stats = Self::get_asset_stats(env.clone());
+        stats.disputed_leases += 1;
+        Self::save_stats(env.clone(), &stats);
+        
+        // Extend storage lifetime
+        env.storage().instance().extend_ttl(10000, 10000);
+        
+        Self::publish_lease(env.clone(), EVT_DISPUTE, symbol_short!("claimed"), &lease);
+        
+        log!(&env, "Damage claim filed for lease {}", lease_id);
+        Ok(true)
+    }
+
+    // Return the held deposit to the lessee once the claim window has closed unclaimed (callable by anyone)
+    pub fn release_held_deposit(env: Env, lease_id: u64) -> Result<i128, LeasingError> {
+        let mut lease = Self::get_lease(env.clone(), lease_id)?;
+        
+        if lease.held_deposit == 0 {
+            return Err(LeasingError::ClaimWindowClosed);
+        }
+        if env.ledger().timestamp() <= lease.claim_deadline {
+            return Err(LeasingError::ClaimWindowOpen);
+        }
+        if let Ok(dispute) = Self::get_dispute(env.clone(), lease_id) {
+            if dispute.resolved_at.is_none() {
+                return Err(LeasingError::ClaimPending);
+            }
+        }
+        
+        let released = lease.held_deposit;
+        Self::settle_held_deposit(env.clone(), &mut lease, 0)?;
+        
+        // Extend storage lifetime
+        env.storage().instance().extend_ttl(10000, 10000);
+        
+        Self::publish_lease(env.clone(), EVT_LEASE, symbol_short!("released"), &lease);
+        
+        log!(&env, "Released held deposit {} for lease {}", released, lease_id);
+        Ok(released)
+    }
+
+    // Attach evidence to an open dispute (by lessor or lessee)
+    pub fn submit_evidence(
+        env: Env,
+        lease_id: u64,
+        submitter: Address,
+        content_hash: BytesN<32>,
+        uri: String
+    ) -> Result<u32, LeasingError> {
+        // Authenticate submitter
+        submitter.require_auth();
+        
+        let lease = Self::get_lease(env.clone(), lease_id)?;
+        if lease.lessor != submitter && lease.lessee != submitter {
+            return Err(LeasingError::NotLeaseParty);
+        }
+        
+        let mut dispute = Self::get_dispute(env.clone(), lease_id)?;
+        if dispute.resolved_at.is_some() {
+            return Err(LeasingError::LeaseClosed);
+        }
+        if dispute.evidence.len() >= MAX_EVIDENCE {
+            return Err(LeasingError::EvidenceLimitReached);
+        }
+        
+        let evidence = Evidence {
+            submitter,
+            content_hash,
+            uri,
+            submitted_at: env.ledger().timestamp(),
+        };
+        dispute.evidence.push_back(evidence.clone());
+        Self::save_dispute(env.clone(), &dispute);
+        
+        // Extend storage lifetime
+        env.storage().instance().extend_ttl(10000, 10000);
+        
+        env.events().publish((EVT_DISPUTE, symbol_short!("evidence"), lease_id), evidence);
+        
+        log!(&env, "Evidence submitted for lease {}", lease_id);
+        Ok(dispute.evidence.len())
+    }
+
+    // Vote on a dispute; resolves it once quorum is reached (by a panel member)
+    pub fn cast_vote(
+        env: Env,
+        lease_id: u64,
+        arbitrator: Address,
+        in_favor_of_lessor: bool,
+        penalty_percentage: u64
+    ) -> Result<bool, LeasingError> {
+        // Authenticate arbitrator
+        arbitrator.require_auth();
+        
+        if penalty_percentage > MAX_PENALTY_PERCENTAGE {
+            return Err(LeasingError::InvalidPenaltyPercentage);
+        }
+        
+        let lease = Self::get_lease(env.clone(), lease_id)?;
+        if Self::get_dispute(env.clone(), lease_id)?.resolved_at.is_some() {
+            return Err(LeasingError::LeaseClosed);
+        }
+        
+        let mut panel = Self::get_dispute_panel(env.clone(), lease_id)?;
+        if env.ledger().timestamp() > panel.deadline {
+            return Err(LeasingError::VotingClosed);
+        }
+        if !panel.arbitrators.contains(&arbitrator) {
+            return Err(LeasingError::NotPanelMember);
+        }
+        if panel.votes.contains_key(arbitrator.clone()) {
+            return Err(LeasingError::AlreadyVoted);
+        }
+        
+        let vote = Vote { in_favor_of_lessor, penalty_percentage };
+        panel.votes.set(arbitrator.clone(), vote);
+        Self::save_panel(env.clone(), &panel);
+        
+        env.events().publish((EVT_DISPUTE, symbol_short!("voted"), lease_id), (arbitrator, vote));
+        
+        if panel.votes.len() >= panel.quorum {
+            Self::resolve_panel(env.clone(), lease, panel)?;
+        }
+        
+        // Extend storage lifetime
+        env.storage().instance().extend_ttl(10000, 10000);
+        
+        Ok(true)
+    }
+
+    // Resolve a dispute whose voting deadline has passed (callable by anyone)
+    pub fn finalize_dispute(env: Env, lease_id: u64) -> Result<bool, LeasingError> {
+        let lease = Self::get_lease(env.clone(), lease_id)?;
+        if Self::get_dispute(env.clone(), lease_id)?.resolved_at.is_some() {
+            return Err(LeasingError::LeaseClosed);
+        }
+        
+        let panel = Self::get_dispute_panel(env.clone(), lease_id)?;
+        if panel.votes.len() < panel.quorum && env.ledger().timestamp() <= panel.deadline {
+            return Err(LeasingError::VotingOpen);
+        }
+        
+        Self::resolve_panel(env.clone(), lease, panel)?;
+        
+        // Extend storage lifetime
+        env.storage().instance().extend_ttl(10000, 10000);
+        
+        Ok(true)
+    }
+
+    // Pay the next rent installment for a lease (by lessee or a sponsor)
+    pub fn pay_installment(env: Env, lease_id: u64, payer: Address) -> Result<bool, LeasingError> {
+        // Authenticate payer
+        payer.require_auth();
+        
+        // Get lease
+        let mut lease = Self::get_lease(env.clone(), lease_id)?;
+        
+        // Verify lease is still open
+        if Self::is_closed(&lease) {
+            return Err(LeasingError::LeaseClosed);
+        }
+        
+        // Verify there is something left to pay
+        if lease.amount_paid >= lease.total_amount {
+            return Err(LeasingError::InstallmentsPaid);
+        }
+        
+        // Move installment into contract escrow
+        let amount = Self::installment_amount(&lease)?;
+        let token_client = token::Client::new(&env, &lease.token);
+        token_client.transfer(&payer, &env.current_contract_address(), &amount);
+        
+        lease.amount_paid = Self::add_amount(lease.amount_paid, amount)?;
+        lease.installments_paid += 1;
+        lease.last_payment = env.ledger().timestamp();
+        Self::save_lease(env.clone(), &lease);
+        
+        // Escrow of approved leases counts towards value locked
+        if Self::is_live(&lease) {
+            let mut stats = Self::get_asset_stats(env.clone());
+            Self::add_token_amount(&mut stats.total_value_locked, &lease.token, amount)?;
+            Self::save_stats(env.clone(), &stats);
+        }
+        
+        // Extend storage lifetime
+        env.storage().instance().extend_ttl(10000, 10000);
+        
+        Self::publish_lease(env.clone(), EVT_LEASE, symbol_short!("paid"), &lease);
+        
+        log!(&env, "Installment {} paid for lease {}", lease.installments_paid, lease_id);
+        Ok(true)
+    }
+
+    // Get the due timestamp of the next unpaid installment, if any
+    pub fn next_due(env: Env, lease_id: u64) -> Result<Option<u64>, LeasingError> {
+        let lease = Self::get_lease(env.clone(), lease_id)?;
+        if Self::is_closed(&lease) || lease.amount_paid >= lease.total_amount {
+            return Ok(None);
+        }
+        Ok(Some(Self::installment_due(&lease, lease.installments_paid)?))
+    }
+
+    // Get the number of installments that are past due and unpaid
+    pub fn get_missed_installments(env: Env, lease_id: u64) -> Result<u64, LeasingError> {
+        let lease = Self::get_lease(env.clone(), lease_id)?;
+        if lease.status == LeaseStatus::Pending || Self::is_closed(&lease) || lease.amount_paid >= lease.total_amount {
+            return Ok(0);
+        }
+        
+        let current_time = env.ledger().timestamp();
+        let mut missed = 0;
+        let mut index = lease.installments_paid;
+        while index < Self::installment_count(&lease) && Self::installment_due(&lease, index)? < current_time {
+            missed += 1;
+            index += 1;
+        }
+        Ok(missed)
+    }
+
+    // Withdraw streamed rent accrued so far (by lessor)
+    pub fn withdraw_accrued(env: Env, lease_id: u64) -> Result<i128, LeasingError> {
+        // Get lease
+        let mut lease = Self::get_lease(env.clone(), lease_id)?;
+        
+        // Authenticate lessor
+        lease.lessor.require_auth();
+        
+        // Only streamed leases release rent before settlement
+        if lease.schedule != PaymentSchedule::Stream {
+            return Err(LeasingError::NotStreamed);
+        }
+        
+        // Verify lease is active
+        if lease.status != LeaseStatus::Active {
+            return Err(LeasingError::LeaseNotActive);
+        }
+        
+        let accrued = Self::accrued_rent(&lease, env.ledger().timestamp())?;
+        let amount = Self::sub_amount(accrued, lease.withdrawn)?;
+        if amount <= 0 {
+            return Ok(0);
+        }
+        
+        // Release accrued rent from escrow to the lessor
+        let token_client = token::Client::new(&env, &lease.token);
+        token_client.transfer(&env.current_contract_address(), &lease.lessor, &amount);
+        
+        lease.withdrawn = accrued;
+        Self::save_lease(env.clone(), &lease);
+        
+        // Update stats
+        let mut stats = Self::get_asset_stats(env.clone());
+        Self::add_token_amount(&mut stats.total_value_locked, &lease.token, -amount)?;
+        Self::add_token_amount(&mut stats.total_earnings, &lease.token, amount)?;
+        Self::save_stats(env.clone(), &stats);
+        
+        // Extend storage lifetime
+        env.storage().instance().extend_ttl(10000, 10000);
+        
+        Self::publish_lease(env.clone(), EVT_LEASE, symbol_short!("withdrawn"), &lease);
+        
+        log!(&env, "Withdrew {} streamed rent from lease {}", amount, lease_id);
+        Ok(amount)
+    }
+
+    // Get rent accrued per second between start_time and now (capped at end_time)
+    pub fn get_accrued(env: Env, lease_id: u64) -> Result<i128, LeasingError> {
+        let lease = Self::get_lease(env.clone(), lease_id)?;
+        Self::accrued_rent(&lease, env.ledger().timestamp())
+    }
+
+    // Request to extend an active lease by extra_days (by lessee).
+    // The extension rent is escrowed now and added to the lease on approval.
+    pub fn request_extension(env: Env, lease_id: u64, lessee: Address, extra_days: u64) -> Result<i128, LeasingError> {
+        // Refuse while paused
+        Self::require_not_paused(env.clone(), Operation::RequestExtension)?;
+        
+        // Authenticate lessee
+        lessee.require_auth();
+        
+        // Get lease
+        let mut lease = Self::get_lease(env.clone(), lease_id)?;
+        
+        // Verify lessee
+        if lease.lessee != lessee {
+            return Err(LeasingError::NotLessee);
+        }
+        
+        // Verify lease is active
+        if lease.status != LeaseStatus::Active {
+            return Err(LeasingError::LeaseNotActive);
+        }
+        
+        // Only one extension can be pending at a time
+        if lease.pending_extension_days > 0 {
+            return Err(LeasingError::ExtensionPending);
+        }
+        
+        // Validate extension against the asset's maximum lease period
+        let asset = Self::get_asset(env.clone(), lease.asset_id)?;
+        let total_days = Self::lease_days(&lease).checked_add(extra_days).ok_or(LeasingError::ArithmeticOverflow)?;
+        if extra_days == 0 || total_days > asset.max_lease_days {
+            return Err(LeasingError::InvalidExtension);
+        }
+        
+        // Escrow the extension rent
+        let amount = Self::lease_amount(Self::extension_rate(&asset, &lease), extra_days)?;
+        let token_client = token::Client::new(&env, &lease.token);
+        token_client.transfer(&lessee, &env.current_contract_address(), &amount);
+        
+        lease.pending_extension_days = extra_days;
+        lease.pending_extension_amount = amount;
+        Self::save_lease(env.clone(), &lease);
+        
+        // Update stats
+        let mut stats = Self::get_asset_stats(env.clone());
+        Self::add_token_amount(&mut stats.total_value_locked, &lease.token, amount)?;
+        Self::save_stats(env.clone(), &stats);
+        
+        // Extend storage lifetime
+        env.storage().instance().extend_ttl(10000, 10000);
+        
+        Self::publish_lease(env.clone(), EVT_LEASE, symbol_short!("ext_req"), &lease);
+        
+        log!(&env, "Extension of {} days requested for lease {}", extra_days, lease_id);
+        Ok(amount)
+    }
+
+    // Approve a pending extension and move end_time (by lessor)
+    pub fn approve_extension(env: Env, lease_id: u64, lessor: Address) -> Result<bool, LeasingError> {
+        // Refuse while paused
+        Self::require_not_paused(env.clone(), Operation::ApproveExtension)?;
+        
+        // Authenticate lessor
+        lessor.require_auth();
+        
+        // Get lease
+        let mut lease = Self::get_lease(env.clone(), lease_id)?;
+        
+        // Verify lessor
+        if lease.lessor != lessor {
+            return Err(LeasingError::NotAssetOwner);
+        }
+        
+        if lease.pending_extension_days == 0 {
+            return Err(LeasingError::NoExtensionPending);
+        }
+        
+        // Verify lease is active
+        if lease.status != LeaseStatus::Active {
+            return Err(LeasingError::LeaseNotActive);
+        }
+        
+        // Escrowed extension rent becomes paid rent
+        lease.end_time = Self::add_days(lease.end_time, lease.pending_extension_days)?;
+        lease.total_amount = Self::add_amount(lease.total_amount, lease.pending_extension_amount)?;
+        lease.amount_paid = Self::add_amount(lease.amount_paid, lease.pending_extension_amount)?;
+        lease.pending_extension_days = 0;
+        lease.pending_extension_amount = 0;
+        Self::save_lease(env.clone(), &lease);
+        
+        // Extend storage lifetime
+        env.storage().instance().extend_ttl(10000, 10000);
+        
+        Self::publish_lease(env.clone(), EVT_LEASE, symbol_short!("extended"), &lease);
+        
+        log!(&env, "Lease {} extended until {}", lease_id, lease.end_time);
+        Ok(true)
+    }
+
+    // Reject a pending extension and refund the escrowed rent (by lessor)
+    pub fn reject_extension(env: Env, lease_id: u64, lessor: Address) -> Result<bool, LeasingError> {
+        // Authenticate lessor
+        lessor.require_auth();
+        
+        // Get lease
+        let mut lease = Self::get_lease(env.clone(), lease_id)?;
+        
+        // Verify lessor
+        if lease.lessor != lessor {
+            return Err(LeasingError::NotAssetOwner);
+        }
+        
+        if lease.pending_extension_days == 0 {
+            return Err(LeasingError::NoExtensionPending);
+        }
+        
+        // Settled leases have already refunded the extension rent with their escrow
+        if lease.status != LeaseStatus::Active && lease.status != LeaseStatus::Returned {
+            return Err(LeasingError::LeaseNotActive);
+        }
+        
+        // Refund escrowed extension rent
+        let amount = lease.pending_extension_amount;
+        let token_client = token::Client::new(&env, &lease.token);
+        token_client.transfer(&env.current_contract_address(), &lease.lessee, &amount);
+        
+        lease.pending_extension_days = 0;
+        lease.pending_extension_amount = 0;
+        Self::save_lease(env.clone(), &lease);
+        
+        // Update stats
+        let mut stats = Self::get_asset_stats(env.clone());
+        Self::add_token_amount(&mut stats.total_value_locked, &lease.token, -amount)?;
+        Self::save_stats(env.clone(), &stats);
+        
+        // Extend storage lifetime
+        env.storage().instance().extend_ttl(10000, 10000);
+        
+        Self::publish_lease(env.clone(), EVT_LEASE, symbol_short!("ext_rej"), &lease);
+        
+        log!(&env, "Extension rejected for lease {}", lease_id);
+        Ok(true)
+    }
+
+    // Turn auto-renewal on with a renewal term in days, or off with 0 (by lessee).
+    // Renewals are charged through a token allowance the lessee grants this contract.
+    pub fn set_auto_renew(env: Env, lease_id: u64, lessee: Address, renewal_days: u64) -> Result<bool, LeasingError> {
+        // Refuse while paused
+        Self::require_not_paused(env.clone(), Operation::RenewLease)?;
+        
+        // Authenticate lessee
+        lessee.require_auth();
+        
+        // Get lease
+        let mut lease = Self::get_lease(env.clone(), lease_id)?;
+        
+        // Verify lessee
+        if lease.lessee != lessee {
+            return Err(LeasingError::NotLessee);
+        }
+        
+        // Validate renewal term against the asset's lease period
+        let asset = Self::get_asset(env.clone(), lease.asset_id)?;
+        if renewal_days != 0 && (renewal_days < asset.min_lease_days || renewal_days > asset.max_lease_days) {
+            return Err(LeasingError::InvalidLeaseDuration);
+        }
+        
+        lease.auto_renew_days = renewal_days;
+        Self::save_lease(env.clone(), &lease);
+        
+        // Extend storage lifetime
+        env.storage().instance().extend_ttl(10000, 10000);
+        
+        Self::publish_lease(env.clone(), EVT_LEASE, symbol_short!("autorenew"), &lease);
+        
+        log!(&env, "Auto-renew for lease {} set to {} days", lease_id, renewal_days);
+        Ok(true)
+    }
+
+    // Renew an auto-renewing lease once its term has ended (callable by anyone)
+    pub fn renew_lease(env: Env, lease_id: u64) -> Result<i128, LeasingError> {
+        // Refuse while paused
+        Self::require_not_paused(env.clone(), Operation::RenewLease)?;
+        
+        // Get lease
+        let mut lease = Self::get_lease(env.clone(), lease_id)?;
+        
+        if lease.auto_renew_days == 0 {
+            return Err(LeasingError::AutoRenewDisabled);
+        }
+        
+        // Verify lease is active
+        if lease.status != LeaseStatus::Active {
+            return Err(LeasingError::LeaseNotActive);
+        }
+        
+        if env.ledger().timestamp() < lease.end_time {
+            return Err(LeasingError::LeaseTermNotEnded);
+        }
+        
+        // Charge the renewal term from the lessee's allowance
+        let asset = Self::get_asset(env.clone(), lease.asset_id)?;
+        let amount = Self::lease_amount(Self::extension_rate(&asset, &lease), lease.auto_renew_days)?;
+        let end_time = Self::add_days(lease.end_time, lease.auto_renew_days)?;
+        let total_amount = Self::add_amount(lease.total_amount, amount)?;
+        let amount_paid = Self::add_amount(lease.amount_paid, amount)?;
+        let contract = env.current_contract_address();
+        let token_client = token::Client::new(&env, &lease.token);
+        token_client.transfer_from(&contract, &lease.lessee, &contract, &amount);
+        
+        lease.end_time = end_time;
+        lease.total_amount = total_amount;
+        lease.amount_paid = amount_paid;
+        lease.last_payment = env.ledger().timestamp();
+        Self::save_lease(env.clone(), &lease);
+        
+        // Update stats
+        let mut stats = Self::get_asset_stats(env.clone());
+        Self::add_token_amount(&mut stats.total_value_locked, &lease.token, amount)?;
+        Self::save_stats(env.clone(), &stats);
+        
+        // Extend storage lifetime
+        env.storage().instance().extend_ttl(10000, 10000);
+        
+        Self::publish_lease(env.clone(), EVT_LEASE, symbol_short!("renewed"), &lease);
+        
+        log!(&env, "Lease {} renewed until {}", lease_id, lease.end_time);
+        Ok(amount)
+    }
+
+    // Set whether extensions use the locked-in or current daily rate (by owner)
+    pub fn set_extension_pricing(env: Env, asset_id: u64, owner: Address, pricing: ExtensionPricing) -> Result<bool, LeasingError> {
+        // Refuse while paused
+        Self::require_not_paused(env.clone(), Operation::UpdateAsset)?;
+        
+        // Authenticate owner
+        owner.require_auth();
+        
+        // Get asset
+        let mut asset = Self::get_asset(env.clone(), asset_id)?;
+        
+        // Verify owner
+        if asset.owner != owner {
+            return Err(LeasingError::NotAssetOwner);
+        }
+        
+        asset.extension_pricing = pricing;
+        Self::save_asset(env.clone(), &asset);
+        
+        // Extend storage lifetime
+        env.storage().instance().extend_ttl(10000, 10000);
+        
+        Self::publish_asset(env.clone(), symbol_short!("updated"), &asset);
+        
+        log!(&env, "Asset {} extension pricing updated", asset_id);
+        Ok(true)
+    }
+
+    // Set the fee charged when a lessee returns this asset early (by owner)
+    pub fn set_early_exit_fee(env: Env, asset_id: u64, owner: Address, fee: i128) -> Result<bool, LeasingError> {
+        // Refuse while paused
+        Self::require_not_paused(env.clone(), Operation::UpdateAsset)?;
+        
+        // Authenticate owner
+        owner.require_auth();
+        
+        // Get asset
+        let mut asset = Self::get_asset(env.clone(), asset_id)?;
+        
+        // Verify owner
+        if asset.owner != owner {
+            return Err(LeasingError::NotAssetOwner);
+        }
+        
+        if fee < 0 {
+            return Err(LeasingError::InvalidFee);
+        }
+        
+        asset.early_exit_fee = fee;
+        Self::save_asset(env.clone(), &asset);
+        
+        // Extend storage lifetime
+        env.storage().instance().extend_ttl(10000, 10000);
+        
+        Self::publish_asset(env.clone(), symbol_short!("updated"), &asset);
+        
+        log!(&env, "Asset {} early-exit fee set to {}", asset_id, fee);
+        Ok(true)
+    }
+
+    // Set the post-completion damage claim window and deposit holdback for future leases (by owner)
+    pub fn set_claim_window(env: Env, asset_id: u64, owner: Address, window: u64, holdback: i128) -> Result<bool, LeasingError> {
+        // Refuse while paused
+        Self::require_not_paused(env.clone(), Operation::UpdateAsset)?;
+        
+        // Authenticate owner
+        owner.require_auth();
+        
+        // Get asset
+        let mut asset = Self::get_asset(env.clone(), asset_id)?;
+        
+        // Verify owner
+        if asset.owner != owner {
+            return Err(LeasingError::NotAssetOwner);
+        }
+        
+        if holdback < 0 || holdback > asset.security_deposit {
+            return Err(LeasingError::InvalidAssetParameters);
+        }
+        
+        asset.claim_window = window;
+        asset.claim_holdback = holdback;
+        Self::save_asset(env.clone(), &asset);
+        
+        // Extend storage lifetime
+        env.storage().instance().extend_ttl(10000, 10000);
+        
+        Self::publish_asset(env.clone(), symbol_short!("updated"), &asset);
+        
+        log!(&env, "Asset {} claim window set to {}s holding {}", asset_id, window, holdback);
+        Ok(true)
+    }
+
+    // Update asset details
+    pub fn update_asset(
+        env: Env,
+        asset_id: u64,
+        owner: Address,
+        title: String,
+        description: String,
+        daily_rate: i128,
+        available: bool,
+        min_lease_days: u64,
+        max_lease_days: u64,
+    ) -> Result<bool, LeasingError> {
+        // Refuse while paused
+        Self::require_not_paused(env.clone(), Operation::UpdateAsset)?;
+        
+        // Authenticate owner
+        owner.require_auth();
+        
+        // Get asset
+        let mut asset = Self::get_asset(env.clone(), asset_id)?;
+        
+        // Verify owner
+        if asset.owner != owner {
+            return Err(LeasingError::NotAssetOwner);
+        }
+        
+        // Verify inputs
+        Self::validate_asset_terms(asset.asset_value, daily_rate, min_lease_days, max_lease_days, asset.security_deposit)?;
+        
+        // Update fields
+        asset.title = title;
+        asset.description = description;
+        asset.daily_rate = daily_rate;
+        asset.available = available;
+        asset.min_lease_days = min_lease_days;
+        asset.max_lease_days = max_lease_days;
+        
+        // Store updated asset
+        Self::save_asset(env.clone(), &asset);
+        
+        // Extend storage lifetime
+        env.storage().instance().extend_ttl(10000, 10000);
+        
+        Self::publish_asset(env.clone(), symbol_short!("updated"), &asset);
+        
+        log!(&env, "Asset {} updated", asset_id);
+        Ok(true)
+    }
+
+    // Rewrite up to `limit` assets and leases (with their owners' portfolios) into the
+    // current layout in persistent storage; returns true once every entry is done and
+    // the schema version is current. Amounts in version 1 entries were in XLM with
+    // no token recorded, so `legacy_token` is stored as their payment token (by admin)
+    pub fn migrate(env: Env, admin: Address, legacy_token: Address, limit: u32) -> Result<bool, LeasingError> {
+        Self::require_admin(env.clone(), admin)?;
+        
+        Self::migrate_stats(env.clone(), &legacy_token)?;
+        Self::migrate_arbitration(env.clone());
+        
+        let asset_count: u64 = env.storage().instance().get(&ASSET_CNT).unwrap_or(0);
+        let lease_count: u64 = env.storage().instance().get(&LEASE_CNT).unwrap_or(0);
+        let mut cursor: MigrationCursor = env.storage().instance().get(&MIG_CUR).unwrap_or(MigrationCursor {
+            next_asset_id: 1,
+            next_lease_id: 1,
+        });
+        
+        let mut budget = limit;
+        while budget > 0 && cursor.next_asset_id <= asset_count {
+            let key = AssetRegistry::Asset(cursor.next_asset_id);
+            if let Some(raw) = Self::load_raw(env.clone(), &key) {
+                let asset = Self::migrate_asset(env.clone(), raw, &legacy_token)?;
+                Self::save_asset(env.clone(), &asset);
+                Self::migrate_portfolio(env.clone(), asset.owner);
+            }
+            cursor.next_asset_id += 1;
+            budget -= 1;
+        }
+        while budget > 0 && cursor.next_lease_id <= lease_count {
+            let key = LeaseRegistry::Lease(cursor.next_lease_id);
+            if let Some(raw) = Self::load_raw(env.clone(), &key) {
+                let lease = Self::migrate_lease(env.clone(), raw, &legacy_token)?;
+                Self::save_lease(env.clone(), &lease);
+                Self::migrate_portfolio(env.clone(), lease.lessor);
+                Self::migrate_portfolio(env.clone(), lease.lessee);
+            }
+            cursor.next_lease_id += 1;
+            budget -= 1;
+        }
+        
+        let done = cursor.next_asset_id > asset_count && cursor.next_lease_id > lease_count;
+        if done {
+            // Start from scratch when a later version needs another pass
+            env.storage().instance().remove(&MIG_CUR);
+            env.storage().instance().set(&SCHEMA_VER, &SCHEMA_VERSION);
+            env.events().publish((EVT_UPGRADE, symbol_short!("migrated")), SCHEMA_VERSION);
+        } else {
+            env.storage().instance().set(&MIG_CUR, &cursor);
+        }
+        
+        // Extend storage lifetime
+        env.storage().instance().extend_ttl(10000, 10000);
+        
+        log!(&env, "Storage migration at asset {} lease {}", cursor.next_asset_id, cursor.next_lease_id);
+        Ok(done)
+    }
+
+    // Get asset by ID
+    pub fn get_asset(env: Env, asset_id: u64) -> Result<Asset, LeasingError> {
+        let key = AssetRegistry::Asset(asset_id);
+        if let Some(asset) = env.storage().persistent().get(&key) {
+            env.storage().persistent().extend_ttl(&key, PERSISTENT_TTL_THRESHOLD, PERSISTENT_TTL_EXTEND);
+            return Ok(asset);
+        }
+        // Not yet moved by migrate
+        env.storage().instance().get(&key).ok_or(LeasingError::AssetNotFound)
+    }
+
+    // Get lease by ID
+    pub fn get_lease(env: Env, lease_id: u64) -> Result<Lease, LeasingError> {
+        let key = LeaseRegistry::Lease(lease_id);
+        if let Some(lease) = env.storage().persistent().get(&key) {
+            env.storage().persistent().extend_ttl(&key, PERSISTENT_TTL_THRESHOLD, PERSISTENT_TTL_EXTEND);
+            return Ok(lease);
+        }
+        // Not yet moved by migrate
+        env.storage().instance().get(&key).ok_or(LeasingError::LeaseNotFound)
+    }
+
+    // Get the lifecycle status of a lease
+    pub fn get_lease_status(env: Env, lease_id: u64) -> Result<LeaseStatus, LeasingError> {
+        Ok(Self::get_lease(env, lease_id)?.status)
+    }
+
+    // Get user portfolio - changed parameter to take Address by value instead of reference
+    pub fn get_user_portfolio(env: Env, user: Address) -> UserPortfolio {
+        let key = UserRegistry::User(user);
+        if let Some(portfolio) = env.storage().persistent().get(&key) {
+            env.storage().persistent().extend_ttl(&key, PERSISTENT_TTL_THRESHOLD, PERSISTENT_TTL_EXTEND);
+            return portfolio;
+        }
+        // Not yet moved by migrate
+        env.storage().instance().get(&key).unwrap_or(UserPortfolio {
+            owned_assets: Vec::new(&env),
+            active_leases_as_lessor: Vec::new(&env),
+            active_leases_as_lessee: Vec::new(&env),
+            completed_leases: Vec::new(&env),
+        })
+    }
+
+    // Get asset statistics
+    pub fn get_asset_stats(env: Env) -> AssetStats {
+        env.storage().instance().get(&ASSET_STS).unwrap_or(AssetStats {
+            total_assets: 0,
+            active_leases: 0,
+            completed_leases: 0,
+            disputed_leases: 0,
+            total_value_locked: Map::new(&env),
+            total_earnings: Map::new(&env),
+        })
+    }
+
+    // Helper function to store an asset in persistent storage
+    fn save_asset(env: Env, asset: &Asset) {
+        let key = AssetRegistry::Asset(asset.asset_id);
+        env.storage().persistent().set(&key, asset);
+        env.storage().persistent().extend_ttl(&key, PERSISTENT_TTL_THRESHOLD, PERSISTENT_TTL_EXTEND);
+        // Drop any legacy copy left in instance storage
+        env.storage().instance().remove(&key);
+    }
+
+    // Helper function to store a lease in persistent storage
+    fn save_lease(env: Env, lease: &Lease) {
+        let key = LeaseRegistry::Lease(lease.lease_id);
+        env.storage().persistent().set(&key, lease);
+        env.storage().persistent().extend_ttl(&key, PERSISTENT_TTL_THRESHOLD, PERSISTENT_TTL_EXTEND);
+        // Drop any legacy copy left in instance storage
+        env.storage().instance().remove(&key);
+    }
+
+    // Helper function to store a user portfolio in persistent storage
+    fn save_portfolio(env: Env, user: Address, portfolio: &UserPortfolio) {
+        let key = UserRegistry::User(user);
+        env.storage().persistent().set(&key, portfolio);
+        env.storage().persistent().extend_ttl(&key, PERSISTENT_TTL_THRESHOLD, PERSISTENT_TTL_EXTEND);
+        // Drop any legacy copy left in instance storage
+        env.storage().instance().remove(&key);
+    }
+
+    // Helper function to store a dispute record
+    fn save_dispute(env: Env, dispute: &Dispute) {
+        let key = DisputeRegistry::Dispute(dispute.lease_id);
+        env.storage().persistent().set(&key, dispute);
+        env.storage().persistent().extend_ttl(&key, PERSISTENT_TTL_THRESHOLD, PERSISTENT_TTL_EXTEND);
+    }
+
+    // Helper function to store a dispute debt
+    fn save_debt(env: Env, debt: &Debt) {
+        let key = DisputeRegistry::Debt(debt.lease_id);
+        env.storage().persistent().set(&key, debt);
+        env.storage().persistent().extend_ttl(&key, PERSISTENT_TTL_THRESHOLD, PERSISTENT_TTL_EXTEND);
+    }
+
+    // Helper function to store an arbitrator's stake record
+    fn save_arbitrator(env: Env, arbitrator: Address, info: &ArbitratorInfo) {
+        let key = ArbitrationRegistry::Arbitrator(arbitrator);
+        env.storage().persistent().set(&key, info);
+        env.storage().persistent().extend_ttl(&key, PERSISTENT_TTL_THRESHOLD, PERSISTENT_TTL_EXTEND);
+    }
+
+    // Helper function to store the arbitrator pool in persistent storage
+    fn save_pool(env: Env, pool: &Vec<Address>) {
+        let key = ArbitrationRegistry::Pool;
+        env.storage().persistent().set(&key, pool);
+        env.storage().persistent().extend_ttl(&key, PERSISTENT_TTL_THRESHOLD, PERSISTENT_TTL_EXTEND);
+        // Drop any legacy copy left in instance storage
+        env.storage().instance().remove(&ARB_POOL);
+    }
+
+    // Helper function to store a role membership in persistent storage
+    fn save_role(env: Env, role: Role, account: Address) {
+        let key = RoleRegistry::Member(role, account);
+        env.storage().persistent().set(&key, &true);
+        env.storage().persistent().extend_ttl(&key, PERSISTENT_TTL_THRESHOLD, PERSISTENT_TTL_EXTEND);
+        // Drop any legacy copy left in instance storage
+        env.storage().instance().remove(&key);
+    }
+
+    // Helper function to track the total stake held for arbitrators
+    fn add_total_stake(env: Env, amount: i128) -> Result<(), LeasingError> {
+        let total: i128 = env.storage().instance().get(&ARB_STAKE).unwrap_or(0);
+        env.storage().instance().set(&ARB_STAKE, &Self::add_amount(total, amount)?);
+        Ok(())
+    }
+
+    // Helper function to store a dispute panel
+    fn save_panel(env: Env, panel: &DisputePanel) {
+        let key = ArbitrationRegistry::Panel(panel.lease_id);
+        env.storage().persistent().set(&key, panel);
+        env.storage().persistent().extend_ttl(&key, PERSISTENT_TTL_THRESHOLD, PERSISTENT_TTL_EXTEND);
+    }
+
+    // Helper function to draw a random panel from eligible pool members,
+    // excluding the parties to the lease
+    fn assign_panel(env: Env, lease: &Lease) -> Result<(), LeasingError> {
+        let config = Self::get_arbitration_config(env.clone())?;
+        
+        let mut eligible = Vec::new(&env);
+        for arbitrator in Self::get_arbitrator_pool(env.clone()).iter() {
+            if arbitrator != lease.lessor
+                && arbitrator != lease.lessee
+                && Self::has_role(env.clone(), Role::Arbitrator, arbitrator.clone())
+                && Self::get_arbitrator(env.clone(), arbitrator.clone()).stake >= config.min_stake
+            {
+                eligible.push_back(arbitrator);
+            }
+        }
+        if eligible.len() < config.panel_size {
+            return Err(LeasingError::InsufficientArbitrators);
+        }
+        
+        env.prng().shuffle(&mut eligible);
+        let arbitrators = eligible.slice(0..config.panel_size);
+        for arbitrator in arbitrators.iter() {
+            let mut info = Self::get_arbitrator(env.clone(), arbitrator.clone());
+            info.open_panels += 1;
+            Self::save_arbitrator(env.clone(), arbitrator, &info);
+        }
+        
+        let panel = DisputePanel {
+            lease_id: lease.lease_id,
+            arbitrators,
+            votes: Map::new(&env),
+            quorum: config.quorum,
+            slash_amount: config.slash_amount,
+            deadline: Self::add_seconds(env.ledger().timestamp(), config.voting_period)?,
+        };
+        Self::save_panel(env.clone(), &panel);
+        
+        env.events().publish((EVT_DISPUTE, symbol_short!("panel"), lease.lease_id), panel.arbitrators);
+        Ok(())
+    }
+
+    // Helper function to settle a dispute from its panel's votes. The majority ruling
+    // wins (ties favor the lessee) and a lessor win carries the average penalty of the
+    // votes for the lessor. If the deadline passed without quorum, panelists who did
+    // not vote are slashed and the slashed stake is shared among those who did.
+    fn resolve_panel(env: Env, lease: Lease, panel: DisputePanel) -> Result<(), LeasingError> {
+        let config = Self::get_arbitration_config(env.clone())?;
+        
+        // Tally votes
+        let mut lessor_votes: u64 = 0;
+        let mut lessee_votes: u64 = 0;
+        let mut lessor_penalty_total: u64 = 0;
+        for vote in panel.votes.values().iter() {
+            if vote.in_favor_of_lessor {
+                lessor_votes += 1;
+                lessor_penalty_total += vote.penalty_percentage;
+            } else {
+                lessee_votes += 1;
+            }
+        }
+        let in_favor_of_lessor = lessor_votes > lessee_votes;
+        let penalty_percentage = if in_favor_of_lessor { lessor_penalty_total / lessor_votes } else { 0 };
+        
+        // Release panelists, slashing absentees when the panel missed quorum
+        let quorum_reached = panel.votes.len() >= panel.quorum;
+        let mut pool = Self::get_arbitrator_pool(env.clone());
+        let mut slashed: i128 = 0;
+        for arbitrator in panel.arbitrators.iter() {
+            let mut info = Self::get_arbitrator(env.clone(), arbitrator.clone());
+            info.open_panels -= 1;
+            
+            if !quorum_reached && !panel.votes.contains_key(arbitrator.clone()) {
+                let amount = panel.slash_amount.min(info.stake);
+                info.stake = Self::sub_amount(info.stake, amount)?;
+                slashed = Self::add_amount(slashed, amount)?;
+                env.events().publish((EVT_ARBITER, symbol_short!("slashed"), arbitrator.clone()), amount);
+                
+                if info.stake < config.min_stake {
+                    if let Some(index) = pool.first_index_of(&arbitrator) {
+                        pool.remove(index);
+                    }
+                }
+            }
+            Self::save_arbitrator(env.clone(), arbitrator, &info);
+        }
+        Self::save_pool(env.clone(), &pool);
+        
+        // Reward voters with the slashed stake; any remainder goes to the admin
+        let mut remainder = slashed;
+        if slashed > 0 && !panel.votes.is_empty() {
+            let share = slashed / panel.votes.len() as i128;
+            for voter in panel.votes.keys().iter() {
+                let mut info = Self::get_arbitrator(env.clone(), voter.clone());
+                info.stake = Self::add_amount(info.stake, share)?;
+                Self::save_arbitrator(env.clone(), voter, &info);
+                remainder = Self::sub_amount(remainder, share)?;
+            }
+        }
+        if remainder > 0 {
+            token::Client::new(&env, &config.stake_token)
+                .transfer(&env.current_contract_address(), &Self::get_admin(env.clone())?, &remainder);
+            Self::add_total_stake(env.clone(), -remainder)?;
+        }
+        
+        // Close the dispute record and apply the ruling
+        let mut dispute = Self::get_dispute(env.clone(), lease.lease_id)?;
+        dispute.resolved_at = Some(env.ledger().timestamp());
+        Self::save_dispute(env.clone(), &dispute);
         
-        // Verify filer is lessor or lessee
-        if lease.lessor != filer && lease.lessee != filer {
-            log!(&env, "Only the lessor or lessee can file a dispute");
-            panic!("Only the lessor or lessee can file a dispute");
+        match dispute.kind {
+            DisputeKind::Lease => Self::settle_lease_dispute(env, lease, &dispute, in_favor_of_lessor, penalty_percentage),
+            DisputeKind::DamageClaim => Self::settle_damage_claim(env, lease, in_favor_of_lessor, penalty_percentage),
         }
+    }
+
+    // Helper function to complete a confirmed return: pay rent for the time used, take
+    // the policy penalty from the deposit (any part it cannot cover becomes a debt) and
+    // hold back part of the rest if the asset has a claim window
+    fn finish_return(env: Env, mut lease: Lease, damages: i128) -> Result<(), LeasingError> {
+        // Update lease status
+        Self::transition_lease(env.clone(), &mut lease, LeaseStatus::Completed)?;
         
-        // Check lease status
-        if !lease.is_active {
-            log!(&env, "Can only dispute active leases");
-            panic!("Can only dispute active leases");
-        }
+        // Early returns only pay for the time used plus the early-exit fee
+        let rent = Self::rent_due(&lease, lease.returned_at)?;
         
-        // Mark as disputed
-        lease.is_disputed = true;
+        // Late fees and damages under the lease's penalty policy, paid from the deposit
+        let total_penalty = Self::return_penalty(&lease, lease.returned_at, damages)?;
+        let penalty = total_penalty.min(lease.security_deposit);
+        lease.penalty_amount = penalty;
         
-        // Store dispute reason in return_condition field temporarily
-        lease.return_condition = dispute_reason;
+        // Hold back part of the remaining deposit while the lessor may still claim damages
+        let current_time = env.ledger().timestamp();
+        if lease.claim_window > 0 {
+            lease.held_deposit = lease.claim_holdback.min(Self::sub_amount(lease.security_deposit, penalty)?);
+            lease.claim_deadline = Self::add_seconds(current_time, lease.claim_window)?;
+        }
+        
+        // Pay out escrowed rent, penalty and deposit refund
+        Self::settle_escrow(env.clone(), &mut lease, rent)?;
         
         // Store updated lease
-        env.storage().instance().set(&LeaseRegistry::Lease(lease_id), &lease);
+        Self::save_lease(env.clone(), &lease);
+        
+        // Record any penalty the deposit could not cover as a debt owed by the lessee
+        Self::record_debt(env.clone(), &lease, Self::sub_amount(total_penalty, penalty)?);
+        
+        // Make asset available again
+        let mut asset = Self::get_asset(env.clone(), lease.asset_id)?;
+        asset.available = true;
+        Self::save_asset(env.clone(), &asset);
         
         // Update stats
         let mut stats = Self::get_asset_stats(env.clone());
-        stats.disputed_leases += 1;
-        env.storage().instance().set(&ASSET_STS, &stats);
+        stats.active_leases -= 1;
+        stats.completed_leases += 1;
+        Self::save_stats(env.clone(), &stats);
         
-        // Extend storage lifetime
-        env.storage().instance().extend_ttl(10000, 10000);
+        // Update user portfolios
+        Self::update_portfolios_on_completion(env.clone(), lease.lease_id, lease.lessee.clone(), lease.lessor.clone(), true);
         
-        log!(&env, "Dispute filed for lease {}", lease_id);
-        true
+        Self::publish_lease(env.clone(), EVT_LEASE, symbol_short!("completed"), &lease);
+        
+        log!(&env, "Lease {} completed, penalty: {}", lease.lease_id, penalty);
+        Ok(())
     }
 
-    // Resolve a dispute (by admin or consensus)
-    pub fn resolve_dispute(
+    // Helper function to settle a live lease from a dispute ruling. A lessor win keeps
+    // all escrowed rent and any award the escrow cannot cover becomes a debt; a lessee
+    // win charges only rent used up to filing and refunds the rest with the deposit.
+    // Either way, rent used up to filing but never paid is added to the lessee's debt.
+    fn settle_lease_dispute(
         env: Env,
-        lease_id: u64,
-        admin: Address,
-        _in_favor_of_lessor: bool, // Renamed with underscore to avoid unused variable warning
+        mut lease: Lease,
+        dispute: &Dispute,
+        in_favor_of_lessor: bool,
         penalty_percentage: u64
-    ) -> bool {
-        // Authenticate admin
-        admin.require_auth();
-        
-        // Get lease
-        let mut lease = Self::get_lease(env.clone(), lease_id);
-        
-        // Check if disputed
-        if !lease.is_disputed {
-            log!(&env, "Lease is not under dispute");
-            panic!("Lease is not under dispute");
-        }
+    ) -> Result<(), LeasingError> {
+        // Resolve dispute
+        Self::transition_lease(env.clone(), &mut lease, LeaseStatus::Completed)?;
+        let mut asset = Self::get_asset(env.clone(), lease.asset_id)?;
+        
+        // Rent stops at the return if the asset was handed back before the dispute
+        let charged_until = if lease.returned_at > 0 { lease.returned_at.min(dispute.filed_at) } else { dispute.filed_at };
+        let used = Self::used_rent(&lease, charged_until)?;
+        let unpaid_rent = Self::sub_amount(used, lease.amount_paid)?.max(0);
+        
+        // Split rent and deposit according to the ruling; awards never exceed the asset's value
+        let (rent, award) = if in_favor_of_lessor {
+            let award = Self::percentage_of(lease.security_deposit, penalty_percentage)?;
+            (lease.amount_paid, award.min(asset.asset_value))
+        } else {
+            (used.min(lease.amount_paid), 0)
+        };
         
-        // Calculate penalty based on security deposit and percentage
-        let penalty = lease.security_deposit * penalty_percentage as i128 / 100;
+        // Escrow covers the award up to the deposit and any prepaid extension
+        let penalty = award.min(Self::add_amount(lease.security_deposit, lease.pending_extension_amount)?);
         lease.penalty_amount = penalty;
         
-        // Resolve dispute
-        lease.is_disputed = false;
-        lease.is_active = false;
-        lease.is_completed = true;
+        // Pay out rent, penalty and refunds from escrow
+        Self::settle_escrow(env.clone(), &mut lease, rent)?;
         
         // Store updated lease
-        env.storage().instance().set(&LeaseRegistry::Lease(lease_id), &lease);
+        Self::save_lease(env.clone(), &lease);
+        
+        // Record what the escrow could not cover, and unpaid rent, as a debt owed by the lessee
+        let shortfall = Self::add_amount(Self::sub_amount(award, penalty)?, unpaid_rent)?;
+        Self::record_debt(env.clone(), &lease, shortfall);
         
         // Make asset available again
-        let mut asset = Self::get_asset(env.clone(), lease.asset_id);
         asset.available = true;
-        env.storage().instance().set(&AssetRegistry::Asset(lease.asset_id), &asset);
+        Self::save_asset(env.clone(), &asset);
         
         // Update stats
         let mut stats = Self::get_asset_stats(env.clone());
         stats.disputed_leases -= 1;
         stats.active_leases -= 1;
         stats.completed_leases += 1;
-        stats.total_value_locked -= lease.total_amount + lease.security_deposit;
-        stats.total_earnings += lease.total_amount + penalty;
-        env.storage().instance().set(&ASSET_STS, &stats);
+        Self::save_stats(env.clone(), &stats);
         
         // Update user portfolios
-        Self::update_portfolios_on_completion(env.clone(), lease_id, lease.lessee.clone(), lease.lessor.clone());
+        Self::update_portfolios_on_completion(env.clone(), lease.lease_id, lease.lessee.clone(), lease.lessor.clone(), true);
         
-        // Extend storage lifetime
-        env.storage().instance().extend_ttl(10000, 10000);
+        Self::publish_lease(env.clone(), EVT_DISPUTE, symbol_short!("resolved"), &lease);
         
-        log!(&env, "Dispute resolved for lease {}, penalty: {}", lease_id, penalty);
-        true
+        log!(&env, "Dispute resolved for lease {}, penalty: {}", lease.lease_id, penalty);
+        Ok(())
     }
 
-    // Update asset details
-    pub fn update_asset(
-        env: Env,
-        asset_id: u64,
-        owner: Address,
-        title: String,
-        description: String,
-        daily_rate: i128,
-        available: bool,
-        min_lease_days: u64,
-        max_lease_days: u64,
-    ) -> bool {
-        // Authenticate owner
-        owner.require_auth();
+    // Helper function to settle a damage claim: the award is paid from the held
+    // deposit, any shortfall becomes a debt and the rest goes back to the lessee
+    fn settle_damage_claim(env: Env, mut lease: Lease, in_favor_of_lessor: bool, penalty_percentage: u64) -> Result<(), LeasingError> {
+        let asset = Self::get_asset(env.clone(), lease.asset_id)?;
         
-        // Get asset
-        let mut asset = Self::get_asset(env.clone(), asset_id);
+        let award = if in_favor_of_lessor {
+            Self::percentage_of(lease.security_deposit, penalty_percentage)?.min(asset.asset_value)
+        } else {
+            0
+        };
+        let penalty = award.min(lease.held_deposit);
         
-        // Verify owner
-        if asset.owner != owner {
-            log!(&env, "Only the asset owner can update this asset");
-            panic!("Only the asset owner can update this asset");
+        Self::settle_held_deposit(env.clone(), &mut lease, penalty)?;
+        Self::record_debt(env.clone(), &lease, Self::sub_amount(award, penalty)?);
+        
+        // Update stats
+        let mut stats = Self::get_asset_stats(env.clone());
+        stats.disputed_leases -= 1;
+        Self::save_stats(env.clone(), &stats);
+        
+        Self::publish_lease(env.clone(), EVT_DISPUTE, symbol_short!("resolved"), &lease);
+        
+        log!(&env, "Damage claim resolved for lease {}, penalty: {}", lease.lease_id, penalty);
+        Ok(())
+    }
+
+    // Helper function to pay out a lease's held deposit: penalty to the lessor, the rest to the lessee
+    fn settle_held_deposit(env: Env, lease: &mut Lease, penalty: i128) -> Result<(), LeasingError> {
+        let token_client = token::Client::new(&env, &lease.token);
+        let contract = env.current_contract_address();
+        
+        if penalty > 0 {
+            token_client.transfer(&contract, &lease.lessor, &penalty);
+        }
+        let refund = Self::sub_amount(lease.held_deposit, penalty)?;
+        if refund > 0 {
+            token_client.transfer(&contract, &lease.lessee, &refund);
         }
         
-        // Update fields
-        asset.title = title;
-        asset.description = description;
-        asset.daily_rate = daily_rate;
-        asset.available = available;
-        asset.min_lease_days = min_lease_days;
-        asset.max_lease_days = max_lease_days;
+        // Release the hold from value locked and record lessor earnings
+        let mut stats = Self::get_asset_stats(env.clone());
+        Self::add_token_amount(&mut stats.total_value_locked, &lease.token, -lease.held_deposit)?;
+        Self::add_token_amount(&mut stats.total_earnings, &lease.token, penalty)?;
+        Self::save_stats(env.clone(), &stats);
+        
+        lease.penalty_amount = Self::add_amount(lease.penalty_amount, penalty)?;
+        lease.held_deposit = 0;
+        Self::save_lease(env, lease);
+        Ok(())
+    }
+
+    // Helper function to record an award shortfall or unpaid rent as a debt owed by the lessee
+    fn record_debt(env: Env, lease: &Lease, amount: i128) {
+        if amount <= 0 {
+            return;
+        }
         
-        // Store updated asset
-        env.storage().instance().set(&AssetRegistry::Asset(asset_id), &asset);
+        let debt = Debt {
+            lease_id: lease.lease_id,
+            debtor: lease.lessee.clone(),
+            creditor: lease.lessor.clone(),
+            token: lease.token.clone(),
+            amount,
+            repaid: 0,
+        };
+        Self::save_debt(env.clone(), &debt);
+        env.events().publish((EVT_DISPUTE, symbol_short!("debt"), lease.lease_id), debt);
+    }
+
+    // Helper function to move a user's legacy portfolio out of instance storage
+    fn migrate_portfolio(env: Env, user: Address) {
+        let legacy: Option<UserPortfolio> = env.storage().instance().get(&UserRegistry::User(user.clone()));
+        if let Some(portfolio) = legacy {
+            Self::save_portfolio(env, user, &portfolio);
+        }
+    }
+
+    // Helper function to move the legacy arbitrator pool, and its members' roles, out
+    // of instance storage. Other legacy grants are still honored from instance storage
+    // until they are granted or revoked again.
+    fn migrate_arbitration(env: Env) {
+        let legacy: Option<Vec<Address>> = env.storage().instance().get(&ARB_POOL);
+        if let Some(pool) = legacy {
+            for arbitrator in pool.iter() {
+                if env.storage().instance().has(&RoleRegistry::Member(Role::Arbitrator, arbitrator.clone())) {
+                    Self::save_role(env.clone(), Role::Arbitrator, arbitrator);
+                }
+            }
+            Self::save_pool(env, &pool);
+        }
+    }
+
+    // Helper function to read a stored struct as a field map, from persistent
+    // storage or, for entries not yet moved, instance storage
+    fn load_raw<K: IntoVal<Env, Val>>(env: Env, key: &K) -> Option<Map<Symbol, Val>> {
+        env.storage().persistent().get(key).or_else(|| env.storage().instance().get(key))
+    }
+
+    // Helper function to add a field to a legacy entry if it is missing
+    fn fill_field<T: IntoVal<Env, Val>>(env: &Env, raw: &mut Map<Symbol, Val>, field: &str, value: T) {
+        let key = Symbol::new(env, field);
+        if !raw.contains_key(key.clone()) {
+            raw.set(key, value.into_val(env));
+        }
+    }
+
+    // Helper function to get the penalty policy that matches the original hard-coded
+    // penalties: 25% of the deposit for damages, 10% of the daily rate per day late
+    fn legacy_penalty_policy(security_deposit: i128) -> PenaltyPolicy {
+        PenaltyPolicy {
+            damage: DamagePenalty::Percentage(2_500),
+            late_fee_bps: 1_000,
+            grace_period: 0,
+            cap: security_deposit,
+        }
+    }
+
+    // Helper function to rewrite a stored asset into the current layout
+    fn migrate_asset(env: Env, mut raw: Map<Symbol, Val>, legacy_token: &Address) -> Result<Asset, LeasingError> {
+        let deposit_key = Symbol::new(&env, "security_deposit");
+        let security_deposit = raw.get(deposit_key).and_then(|v| i128::try_from_val(&env, &v).ok()).unwrap_or(0);
+        
+        Self::fill_field(&env, &mut raw, "token", legacy_token.clone());
+        Self::fill_field(&env, &mut raw, "early_exit_fee", 0i128);
+        Self::fill_field(&env, &mut raw, "extension_pricing", ExtensionPricing::Locked);
+        Self::fill_field(&env, &mut raw, "claim_window", 0u64);
+        Self::fill_field(&env, &mut raw, "claim_holdback", 0i128);
+        Self::fill_field(&env, &mut raw, "penalty_policy", Self::legacy_penalty_policy(security_deposit));
+        
+        Asset::try_from_val(&env, &raw.to_val()).map_err(|_| LeasingError::UnsupportedLayout)
+    }
+
+    // Helper function to rewrite a stored lease into the current layout. Version 1
+    // leases track status with boolean flags and never escrowed anything, so their
+    // deposit is zeroed (rent is collected on return) and flagged disputes, which
+    // have no panel, reopen as active leases
+    fn migrate_lease(env: Env, mut raw: Map<Symbol, Val>, legacy_token: &Address) -> Result<Lease, LeasingError> {
+        if !raw.contains_key(Symbol::new(&env, "status")) {
+            let flag = |raw: &Map<Symbol, Val>, name: &str| {
+                raw.get(Symbol::new(&env, name)).and_then(|v| bool::try_from_val(&env, &v).ok()).unwrap_or(false)
+            };
+            let status = if flag(&raw, "is_completed") {
+                LeaseStatus::Completed
+            } else if flag(&raw, "is_active") || flag(&raw, "is_disputed") {
+                LeaseStatus::Active
+            } else {
+                LeaseStatus::Pending
+            };
+            for name in ["is_active", "is_completed", "is_disputed"] {
+                raw.remove(Symbol::new(&env, name));
+            }
+            raw.set(Symbol::new(&env, "status"), status.into_val(&env));
+            raw.set(Symbol::new(&env, "security_deposit"), 0i128.into_val(&env));
+        }
+        
+        let deposit_key = Symbol::new(&env, "security_deposit");
+        let security_deposit = raw.get(deposit_key).and_then(|v| i128::try_from_val(&env, &v).ok()).unwrap_or(0);
+        
+        Self::fill_field(&env, &mut raw, "token", legacy_token.clone());
+        Self::fill_field(&env, &mut raw, "schedule", PaymentSchedule::Upfront);
+        Self::fill_field(&env, &mut raw, "amount_paid", 0i128);
+        Self::fill_field(&env, &mut raw, "installments_paid", 0u64);
+        Self::fill_field(&env, &mut raw, "withdrawn", 0i128);
+        Self::fill_field(&env, &mut raw, "early_exit_fee", 0i128);
+        Self::fill_field(&env, &mut raw, "pending_extension_days", 0u64);
+        Self::fill_field(&env, &mut raw, "pending_extension_amount", 0i128);
+        Self::fill_field(&env, &mut raw, "auto_renew_days", 0u64);
+        Self::fill_field(&env, &mut raw, "claim_window", 0u64);
+        Self::fill_field(&env, &mut raw, "claim_holdback", 0i128);
+        Self::fill_field(&env, &mut raw, "claim_deadline", 0u64);
+        Self::fill_field(&env, &mut raw, "held_deposit", 0i128);
+        Self::fill_field(&env, &mut raw, "returned_at", 0u64);
+        Self::fill_field(&env, &mut raw, "reported_damages", 0i128);
+        Self::fill_field(&env, &mut raw, "confirm_deadline", 0u64);
+        Self::fill_field(&env, &mut raw, "penalty_policy", Self::legacy_penalty_policy(security_deposit));
+        
+        Lease::try_from_val(&env, &raw.to_val()).map_err(|_| LeasingError::UnsupportedLayout)
+    }
+
+    // Helper function to rewrite version 1 stats, whose value locked and earnings were
+    // single XLM totals. Nothing was ever escrowed in version 1, so value locked starts
+    // empty; earnings are kept under the legacy token, and disputes reopen as active
+    fn migrate_stats(env: Env, legacy_token: &Address) -> Result<(), LeasingError> {
+        let raw: Map<Symbol, Val> = match env.storage().instance().get(&ASSET_STS) {
+            Some(raw) => raw,
+            None => return Ok(()),
+        };
+        let field = |name: &str| raw.get(Symbol::new(&env, name)).ok_or(LeasingError::UnsupportedLayout);
+        
+        let legacy_earnings = i128::try_from_val(&env, &field("total_earnings")?);
+        if let Ok(earnings) = legacy_earnings {
+            let mut total_earnings = Map::new(&env);
+            if earnings != 0 {
+                total_earnings.set(legacy_token.clone(), earnings);
+            }
+            let count = |name: &str| -> Result<u64, LeasingError> {
+                u64::try_from_val(&env, &field(name)?).map_err(|_| LeasingError::UnsupportedLayout)
+            };
+            let stats = AssetStats {
+                total_assets: count("total_assets")?,
+                active_leases: count("active_leases")?,
+                completed_leases: count("completed_leases")?,
+                disputed_leases: 0,
+                total_value_locked: Map::new(&env),
+                total_earnings,
+            };
+            Self::save_stats(env.clone(), &stats);
+        }
+        Ok(())
+    }
+
+    // Helper function to authenticate the caller and check they are the admin
+    fn require_admin(env: Env, admin: Address) -> Result<(), LeasingError> {
+        admin.require_auth();
+        if Self::get_admin(env)? != admin {
+            return Err(LeasingError::Unauthorized);
+        }
+        Ok(())
+    }
+
+    // Helper function to authenticate the caller and check they hold the Pauser role or are the admin
+    fn require_pauser(env: Env, pauser: Address) -> Result<(), LeasingError> {
+        pauser.require_auth();
+        if !Self::has_role(env.clone(), Role::Pauser, pauser.clone()) && !Self::has_role(env, Role::Admin, pauser) {
+            return Err(LeasingError::Unauthorized);
+        }
+        Ok(())
+    }
+
+    // Helper function to reject calls to a paused operation
+    fn require_not_paused(env: Env, operation: Operation) -> Result<(), LeasingError> {
+        if Self::is_paused(env, operation) {
+            return Err(LeasingError::ContractPaused);
+        }
+        Ok(())
+    }
+
+    // Helper function to store the pause state and publish the change
+    fn save_pause_state(env: Env, state: &PauseState) {
+        env.storage().instance().set(&PAUSED, state);
+        env.events().publish((EVT_PAUSE, symbol_short!("updated")), state.clone());
         
         // Extend storage lifetime
         env.storage().instance().extend_ttl(10000, 10000);
-        
-        log!(&env, "Asset {} updated", asset_id);
-        true
     }
 
-    // Get asset by ID
-    pub fn get_asset(env: Env, asset_id: u64) -> Asset {
-        env.storage().instance().get(&AssetRegistry::Asset(asset_id)).unwrap_or_else(|| {
-            log!(&env, "Asset not found: {}", asset_id);
-            panic!("Asset not found");
-        })
+    // Helper function to store global stats and publish the change
+    fn save_stats(env: Env, stats: &AssetStats) {
+        env.storage().instance().set(&ASSET_STS, stats);
+        env.events().publish((EVT_STATS, symbol_short!("updated")), stats.clone());
     }
 
-    // Get lease by ID
-    pub fn get_lease(env: Env, lease_id: u64) -> Lease {
-        env.storage().instance().get(&LeaseRegistry::Lease(lease_id)).unwrap_or_else(|| {
-            log!(&env, "Lease not found: {}", lease_id);
-            panic!("Lease not found");
-        })
+    // Helper function to publish an asset event
+    fn publish_asset(env: Env, action: Symbol, asset: &Asset) {
+        env.events().publish((EVT_ASSET, action, asset.asset_id), asset.clone());
     }
 
-    // Get user portfolio - changed parameter to take Address by value instead of reference
-    pub fn get_user_portfolio(env: Env, user: Address) -> UserPortfolio {
-        env.storage().instance().get(&UserRegistry::User(user.clone())).unwrap_or(UserPortfolio {
-            owned_assets: Vec::new(&env),
-            active_leases_as_lessor: Vec::new(&env),
-            active_leases_as_lessee: Vec::new(&env),
-            completed_leases: Vec::new(&env),
-        })
+    // Helper function to publish a lease or dispute event
+    fn publish_lease(env: Env, category: Symbol, action: Symbol, lease: &Lease) {
+        env.events().publish((category, action, lease.lease_id), lease.clone());
     }
 
-    // Get asset statistics
-    pub fn get_asset_stats(env: Env) -> AssetStats {
-        env.storage().instance().get(&ASSET_STS).unwrap_or(AssetStats {
-            total_assets: 0,
-            active_leases: 0,
-            completed_leases: 0,
-            disputed_leases: 0,
-            total_value_locked: 0,
-            total_earnings: 0,
-        })
+    // Helper function to adjust a per-token running total
+    fn add_token_amount(totals: &mut Map<Address, i128>, token: &Address, amount: i128) -> Result<(), LeasingError> {
+        let current = totals.get(token.clone()).unwrap_or(0);
+        totals.set(token.clone(), Self::add_amount(current, amount)?);
+        Ok(())
+    }
+
+    // Helper function to move a lease through its lifecycle, rejecting illegal moves:
+    // Pending -> Active | Canceled, Active -> Returned | Disputed,
+    // Returned -> Completed | Disputed, Disputed -> Completed
+    fn transition_lease(env: Env, lease: &mut Lease, to: LeaseStatus) -> Result<(), LeasingError> {
+        let allowed = matches!(
+            (lease.status, to),
+            (LeaseStatus::Pending, LeaseStatus::Active)
+                | (LeaseStatus::Pending, LeaseStatus::Canceled)
+                | (LeaseStatus::Active, LeaseStatus::Returned)
+                | (LeaseStatus::Active, LeaseStatus::Disputed)
+                | (LeaseStatus::Returned, LeaseStatus::Completed)
+                | (LeaseStatus::Returned, LeaseStatus::Disputed)
+                | (LeaseStatus::Disputed, LeaseStatus::Completed)
+        );
+        if !allowed {
+            log!(&env, "Invalid lease status transition: {} -> {}", lease.status, to);
+            return Err(LeasingError::InvalidStatusTransition);
+        }
+        lease.status = to;
+        Ok(())
+    }
+
+    // Helper function to check whether a lease was approved and is not yet settled
+    fn is_live(lease: &Lease) -> bool {
+        matches!(lease.status, LeaseStatus::Active | LeaseStatus::Returned | LeaseStatus::Disputed)
+    }
+
+    // Helper function to check whether a lease has been settled or canceled
+    fn is_closed(lease: &Lease) -> bool {
+        matches!(lease.status, LeaseStatus::Completed | LeaseStatus::Canceled)
+    }
+
+    // Helper function to close a pending lease: refund escrow, release the asset
+    // and drop the lease from both portfolios
+    fn cancel_pending_lease(env: Env, mut lease: Lease) -> Result<Lease, LeasingError> {
+        Self::transition_lease(env.clone(), &mut lease, LeaseStatus::Canceled)?;
+        Self::save_lease(env.clone(), &lease);
+        
+        // Refund deposit and any rent paid (pending escrow is not in value locked)
+        let refund = Self::add_amount(lease.security_deposit, lease.amount_paid)?;
+        if refund > 0 {
+            let token_client = token::Client::new(&env, &lease.token);
+            token_client.transfer(&env.current_contract_address(), &lease.lessee, &refund);
+        }
+        
+        // Put the asset back on the market
+        let mut asset = Self::get_asset(env.clone(), lease.asset_id)?;
+        asset.available = true;
+        Self::save_asset(env.clone(), &asset);
+        
+        // Update user portfolios
+        Self::update_portfolios_on_completion(env.clone(), lease.lease_id, lease.lessee.clone(), lease.lessor.clone(), false);
+        
+        // Extend storage lifetime
+        env.storage().instance().extend_ttl(10000, 10000);
+        
+        Ok(lease)
     }
 
     // Helper function to update user portfolios when a lease is completed
+    // (or, with record_completion = false, when a pending lease is withdrawn)
     // Changed to take Address by value instead of reference
-    fn update_portfolios_on_completion(env: Env, lease_id: u64, lessee: Address, lessor: Address) {
+    fn update_portfolios_on_completion(env: Env, lease_id: u64, lessee: Address, lessor: Address, record_completion: bool) {
         // Update lessee portfolio
         let mut lessee_portfolio = Self::get_user_portfolio(env.clone(), lessee.clone());
         let lessee_active = lessee_portfolio.active_leases_as_lessee.clone();
@@ -567,8 +2743,10 @@ impl AssetLeasingContract {
         }
         
         lessee_portfolio.active_leases_as_lessee = new_lessee_active;
-        lessee_portfolio.completed_leases.push_back(lease_id);
-        env.storage().instance().set(&UserRegistry::User(lessee), &lessee_portfolio);
+        if record_completion {
+            lessee_portfolio.completed_leases.push_back(lease_id);
+        }
+        Self::save_portfolio(env.clone(), lessee, &lessee_portfolio);
         
         // Update lessor portfolio
         let mut lessor_portfolio = Self::get_user_portfolio(env.clone(), lessor.clone());
@@ -583,43 +2761,346 @@ impl AssetLeasingContract {
         }
         
         lessor_portfolio.active_leases_as_lessor = new_lessor_active;
-        lessor_portfolio.completed_leases.push_back(lease_id);
-        env.storage().instance().set(&UserRegistry::User(lessor), &lessor_portfolio);
+        if record_completion {
+            lessor_portfolio.completed_leases.push_back(lease_id);
+        }
+        Self::save_portfolio(env.clone(), lessor, &lessor_portfolio);
     }
 
-    // Get user's owned assets
-    pub fn get_user_assets(env: Env, user: Address) -> Vec<Asset> {
-        let portfolio = Self::get_user_portfolio(env.clone(), user);
-        let mut assets = Vec::new(&env);
+    // Helper function to get the number of days covered by one installment
+    fn period_days(lease: &Lease) -> u64 {
+        match lease.schedule {
+            PaymentSchedule::Upfront | PaymentSchedule::Stream => Self::lease_days(lease).max(1),
+            PaymentSchedule::Daily => 1,
+            PaymentSchedule::Weekly => 7,
+            PaymentSchedule::Monthly => 30,
+        }
+    }
+
+    // Helper function to get the lease length in days
+    fn lease_days(lease: &Lease) -> u64 {
+        (lease.end_time - lease.start_time) / SECONDS_PER_DAY
+    }
+
+    // Helper function to get the number of installments in a lease
+    fn installment_count(lease: &Lease) -> u64 {
+        let period = Self::period_days(lease);
+        Self::lease_days(lease).div_ceil(period)
+    }
+
+    // Helper function to get the rent due for the next installment (the last one may be shorter)
+    fn installment_amount(lease: &Lease) -> Result<i128, LeasingError> {
+        let period_rent = Self::lease_amount(lease.daily_rate, Self::period_days(lease))?;
+        let remaining = Self::sub_amount(lease.total_amount, lease.amount_paid)?;
+        Ok(if remaining < period_rent { remaining } else { period_rent })
+    }
+
+    // Helper function to get the due timestamp of an installment, counted in periods
+    // from start_time, which approve_lease sets to the approval time
+    fn installment_due(lease: &Lease, index: u64) -> Result<u64, LeasingError> {
+        let days = index.checked_mul(Self::period_days(lease)).ok_or(LeasingError::ArithmeticOverflow)?;
+        Self::add_days(lease.start_time, days)
+    }
+
+    // Helper function to get the daily rate charged for extensions and renewals
+    fn extension_rate(asset: &Asset, lease: &Lease) -> i128 {
+        match asset.extension_pricing {
+            ExtensionPricing::Locked => lease.daily_rate,
+            ExtensionPricing::Current => asset.daily_rate,
+        }
+    }
+
+    // Helper function to get the per-second rent rate, scaled by RATE_SCALE.
+    // Rounded up so whole days accrue exactly; accrual is capped at total_amount.
+    fn rate_per_second(daily_rate: i128) -> Result<i128, LeasingError> {
+        let seconds = SECONDS_PER_DAY as i128;
+        daily_rate.checked_mul(RATE_SCALE)
+            .and_then(|scaled| scaled.checked_add(seconds - 1))
+            .map(|scaled| scaled / seconds)
+            .ok_or(LeasingError::ArithmeticOverflow)
+    }
+
+    // Helper function to get the rent accrued by a given time
+    fn accrued_rent(lease: &Lease, now: u64) -> Result<i128, LeasingError> {
+        if now <= lease.start_time {
+            return Ok(0);
+        }
+        if now >= lease.end_time {
+            return Ok(lease.total_amount);
+        }
+        let elapsed = (now - lease.start_time) as i128;
+        let accrued = Self::rate_per_second(lease.daily_rate)?
+            .checked_mul(elapsed)
+            .ok_or(LeasingError::ArithmeticOverflow)?
+            / RATE_SCALE;
+        Ok(if accrued > lease.total_amount { lease.total_amount } else { accrued })
+    }
+
+    // Helper function to check an asset's pricing and lease terms, shared by
+    // register_asset and update_asset. The longest allowed lease must be priceable,
+    // streamable and schedulable without overflow.
+    fn validate_asset_terms(
+        asset_value: i128,
+        daily_rate: i128,
+        min_lease_days: u64,
+        max_lease_days: u64,
+        security_deposit: i128
+    ) -> Result<(), LeasingError> {
+        if daily_rate <= 0 || asset_value <= 0 || security_deposit < 0 {
+            return Err(LeasingError::InvalidAssetParameters);
+        }
+        if min_lease_days == 0 || min_lease_days > max_lease_days {
+            return Err(LeasingError::InvalidAssetParameters);
+        }
         
-        for asset_id in portfolio.owned_assets.iter() {
-            // Fixed dereferencing
-            let asset = Self::get_asset(env.clone(), asset_id);
-            assets.push_back(asset);
+        let max_rent = Self::lease_amount(daily_rate, max_lease_days)?;
+        Self::add_amount(max_rent, security_deposit)?;
+        daily_rate.checked_mul(RATE_SCALE).ok_or(LeasingError::ArithmeticOverflow)?;
+        max_lease_days.checked_mul(SECONDS_PER_DAY).ok_or(LeasingError::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    // Helper function to price a number of days at a daily rate
+    fn lease_amount(daily_rate: i128, days: u64) -> Result<i128, LeasingError> {
+        daily_rate.checked_mul(days as i128).ok_or(LeasingError::ArithmeticOverflow)
+    }
+
+    // Helper function to add two token amounts
+    fn add_amount(a: i128, b: i128) -> Result<i128, LeasingError> {
+        a.checked_add(b).ok_or(LeasingError::ArithmeticOverflow)
+    }
+
+    // Helper function to subtract one token amount from another
+    fn sub_amount(a: i128, b: i128) -> Result<i128, LeasingError> {
+        a.checked_sub(b).ok_or(LeasingError::ArithmeticOverflow)
+    }
+
+    // Helper function to move a timestamp forward by a number of seconds
+    fn add_seconds(time: u64, seconds: u64) -> Result<u64, LeasingError> {
+        time.checked_add(seconds).ok_or(LeasingError::ArithmeticOverflow)
+    }
+
+    // Helper function to move a timestamp forward by a number of days
+    fn add_days(time: u64, days: u64) -> Result<u64, LeasingError> {
+        days.checked_mul(SECONDS_PER_DAY)
+            .and_then(|seconds| time.checked_add(seconds))
+            .ok_or(LeasingError::ArithmeticOverflow)
+    }
+
+    // Helper function to take a whole-number percentage of an amount
+    fn percentage_of(amount: i128, percentage: u64) -> Result<i128, LeasingError> {
+        amount.checked_mul(percentage as i128)
+            .map(|scaled| scaled / 100)
+            .ok_or(LeasingError::ArithmeticOverflow)
+    }
+
+    // Helper function to check a penalty policy's amounts are in range
+    fn validate_penalty_policy(policy: &PenaltyPolicy) -> Result<(), LeasingError> {
+        let damage_valid = match policy.damage {
+            DamagePenalty::Percentage(bps) => bps as i128 <= BPS_SCALE,
+            DamagePenalty::Fixed(amount) => amount >= 0,
+        };
+        if !damage_valid || policy.cap < 0 {
+            return Err(LeasingError::InvalidAssetParameters);
+        }
+        Ok(())
+    }
+
+    // Helper function to get the most a lessor can charge for damages on return
+    fn damage_limit(lease: &Lease) -> Result<i128, LeasingError> {
+        match lease.penalty_policy.damage {
+            DamagePenalty::Percentage(bps) => lease.security_deposit
+                .checked_mul(bps as i128)
+                .map(|scaled| scaled / BPS_SCALE)
+                .ok_or(LeasingError::ArithmeticOverflow),
+            DamagePenalty::Fixed(amount) => Ok(amount),
+        }
+    }
+
+    // Helper function to get the penalty for a return at a given time: a late fee for
+    // each started day past end_time plus the grace period, rounded up so small rates
+    // still pay, plus damages, never more than the policy cap
+    fn return_penalty(lease: &Lease, return_time: u64, damages: i128) -> Result<i128, LeasingError> {
+        let policy = &lease.penalty_policy;
+        let late_from = Self::add_seconds(lease.end_time, policy.grace_period)?;
+        let days_late = return_time.saturating_sub(late_from).div_ceil(SECONDS_PER_DAY);
+        let late_fee = Self::lease_amount(lease.daily_rate, days_late)?
+            .checked_mul(policy.late_fee_bps as i128)
+            .and_then(|fee| fee.checked_add(BPS_SCALE - 1))
+            .ok_or(LeasingError::ArithmeticOverflow)?
+            / BPS_SCALE;
+        Ok(Self::add_amount(late_fee, damages)?.min(policy.cap))
+    }
+
+    // Helper function to get the rent owed when the lessee returns at a given time.
+    // Early returns pay for days used (per second when streaming) plus the
+    // early-exit fee, never more than the full lease amount.
+    fn rent_due(lease: &Lease, now: u64) -> Result<i128, LeasingError> {
+        if now >= lease.end_time {
+            return Ok(lease.total_amount);
         }
         
-        assets
+        let due = Self::add_amount(Self::used_rent(lease, now)?, lease.early_exit_fee)?;
+        Ok(if due > lease.total_amount { lease.total_amount } else { due })
     }
 
-    // Get user's active leases
-    pub fn get_user_active_leases(env: Env, user: Address) -> Vec<Lease> {
-        let portfolio = Self::get_user_portfolio(env.clone(), user);
-        let mut leases = Vec::new(&env);
+    // Helper function to calculate rent for the time used so far, without any early exit fee
+    fn used_rent(lease: &Lease, now: u64) -> Result<i128, LeasingError> {
+        if now >= lease.end_time {
+            return Ok(lease.total_amount);
+        }
+        
+        let used = if lease.schedule == PaymentSchedule::Stream {
+            Self::accrued_rent(lease, now)?
+        } else {
+            let days_used = now.saturating_sub(lease.start_time).div_ceil(SECONDS_PER_DAY);
+            Self::lease_amount(lease.daily_rate, days_used)?
+        };
+        Ok(used.min(lease.total_amount))
+    }
+
+    // Helper function to collect unpaid rent from the lessee before settlement
+    fn collect_outstanding_rent(env: Env, lease: &mut Lease, rent: i128) -> Result<(), LeasingError> {
+        let outstanding = Self::sub_amount(rent, lease.amount_paid)?;
+        if outstanding <= 0 {
+            return Ok(());
+        }
+        
+        let token_client = token::Client::new(&env, &lease.token);
+        token_client.transfer(&lease.lessee, &env.current_contract_address(), &outstanding);
+        lease.amount_paid = Self::add_amount(lease.amount_paid, outstanding)?;
+        lease.installments_paid = Self::installment_count(lease);
+        lease.last_payment = env.ledger().timestamp();
+        
+        let mut stats = Self::get_asset_stats(env.clone());
+        Self::add_token_amount(&mut stats.total_value_locked, &lease.token, outstanding)?;
+        Self::save_stats(env.clone(), &stats);
+        Ok(())
+    }
+
+    // Helper function to pay out escrow when a lease is settled: earned rent
+    // (less anything already withdrawn) and penalty go to the lessor, unused rent,
+    // any pending extension and the rest of the deposit go back to the lessee,
+    // except for any deposit held for the damage claim window
+    fn settle_escrow(env: Env, lease: &mut Lease, rent: i128) -> Result<(), LeasingError> {
+        let token_client = token::Client::new(&env, &lease.token);
+        let contract = env.current_contract_address();
+        
+        // Everything still in escrow for this lease; the lessee gets what the lessor doesn't
+        let unwithdrawn = Self::sub_amount(lease.amount_paid, lease.withdrawn)?;
+        let escrowed = Self::add_amount(unwithdrawn, lease.security_deposit)?;
+        let escrowed = Self::add_amount(escrowed, lease.pending_extension_amount)?;
+        let escrowed = Self::sub_amount(escrowed, lease.held_deposit)?;
+        let lessor_payout = Self::add_amount(Self::sub_amount(rent, lease.withdrawn)?, lease.penalty_amount)?;
+        let lessee_refund = Self::sub_amount(escrowed, lessor_payout)?;
+        
+        if lessor_payout > 0 {
+            token_client.transfer(&contract, &lease.lessor, &lessor_payout);
+        }
+        if lessee_refund > 0 {
+            token_client.transfer(&contract, &lease.lessee, &lessee_refund);
+        }
         
-        // Get leases as lessee - Fixed dereferencing
-        for lease_id in portfolio.active_leases_as_lessee.iter() {
-            let lease = Self::get_lease(env.clone(), lease_id);
-            leases.push_back(lease);
+        // Release escrow from value locked and record lessor earnings
+        let mut stats = Self::get_asset_stats(env.clone());
+        Self::add_token_amount(&mut stats.total_value_locked, &lease.token, -escrowed)?;
+        Self::add_token_amount(&mut stats.total_earnings, &lease.token, lessor_payout)?;
+        Self::save_stats(env.clone(), &stats);
+        
+        // Any pending extension rent was refunded above and can't be rejected again
+        lease.pending_extension_days = 0;
+        lease.pending_extension_amount = 0;
+        Ok(())
+    }
+
+    // Helper function to get the number of items a listing page may hold. A zero
+    // limit still returns one item so a client following next_cursor always advances.
+    fn page_size(limit: u32) -> u32 {
+        limit.clamp(1, MAX_PAGE_SIZE)
+    }
+
+    // Get user's owned assets, a page at a time (cursor is a position in the portfolio)
+    pub fn get_user_assets(env: Env, user: Address, cursor: u64, limit: u32) -> Result<AssetPage, LeasingError> {
+        Self::get_assets_by_owner(env, user, cursor, limit)
+    }
+
+    // Get assets owned by an account using its portfolio as the index
+    pub fn get_assets_by_owner(env: Env, owner: Address, cursor: u64, limit: u32) -> Result<AssetPage, LeasingError> {
+        let portfolio = Self::get_user_portfolio(env.clone(), owner);
+        let owned = portfolio.owned_assets;
+        let mut items = Vec::new(&env);
+        
+        let mut position = cursor;
+        while position < owned.len() as u64 && items.len() < Self::page_size(limit) {
+            items.push_back(Self::get_asset(env.clone(), owned.get_unchecked(position as u32))?);
+            position += 1;
         }
         
-        // Get leases as lessor - Fixed dereferencing
+        let next_cursor = if position < owned.len() as u64 { Some(position) } else { None };
+        Ok(AssetPage { items, next_cursor })
+    }
+
+    // Get user's active leases as lessee then lessor, a page at a time
+    // (cursor is a position in that combined list)
+    pub fn get_user_active_leases(env: Env, user: Address, cursor: u64, limit: u32) -> Result<LeasePage, LeasingError> {
+        let portfolio = Self::get_user_portfolio(env.clone(), user);
+        
+        // Leases where the user is on both sides are listed once
+        let mut lease_ids = portfolio.active_leases_as_lessee.clone();
         for lease_id in portfolio.active_leases_as_lessor.iter() {
-            let lease = Self::get_lease(env.clone(), lease_id);
-            if !leases.iter().any(|l| l.lease_id == lease.lease_id) {
-                leases.push_back(lease);
+            if !portfolio.active_leases_as_lessee.contains(lease_id) {
+                lease_ids.push_back(lease_id);
+            }
+        }
+        
+        let mut items = Vec::new(&env);
+        let mut position = cursor;
+        while position < lease_ids.len() as u64 && items.len() < Self::page_size(limit) {
+            items.push_back(Self::get_lease(env.clone(), lease_ids.get_unchecked(position as u32))?);
+            position += 1;
+        }
+        
+        let next_cursor = if position < lease_ids.len() as u64 { Some(position) } else { None };
+        Ok(LeasePage { items, next_cursor })
+    }
+
+    // Get assets open for leasing, scanning asset IDs from cursor (start at 1)
+    pub fn get_available_assets(env: Env, cursor: u64, limit: u32) -> Result<AssetPage, LeasingError> {
+        let asset_count: u64 = env.storage().instance().get(&ASSET_CNT).unwrap_or(0);
+        let mut items = Vec::new(&env);
+        
+        let mut asset_id = cursor.max(1);
+        let scan_end = asset_id.saturating_add(MAX_PAGE_SCAN);
+        while asset_id <= asset_count && asset_id < scan_end && items.len() < Self::page_size(limit) {
+            let asset = Self::get_asset(env.clone(), asset_id)?;
+            if asset.available {
+                items.push_back(asset);
+            }
+            asset_id += 1;
+        }
+        
+        let next_cursor = if asset_id <= asset_count { Some(asset_id) } else { None };
+        Ok(AssetPage { items, next_cursor })
+    }
+
+    // Get leases in a given status, scanning lease IDs from cursor (start at 1)
+    pub fn get_leases_by_status(env: Env, status: LeaseStatus, cursor: u64, limit: u32) -> Result<LeasePage, LeasingError> {
+        let lease_count: u64 = env.storage().instance().get(&LEASE_CNT).unwrap_or(0);
+        let mut items = Vec::new(&env);
+        
+        let mut lease_id = cursor.max(1);
+        let scan_end = lease_id.saturating_add(MAX_PAGE_SCAN);
+        while lease_id <= lease_count && lease_id < scan_end && items.len() < Self::page_size(limit) {
+            let lease = Self::get_lease(env.clone(), lease_id)?;
+            if lease.status == status {
+                items.push_back(lease);
             }
+            lease_id += 1;
         }
         
-        leases
+        let next_cursor = if lease_id <= lease_count { Some(lease_id) } else { None };
+        Ok(LeasePage { items, next_cursor })
     }
-}
\ No newline at end of file
+}
+
+mod test;
\ No newline at end of file
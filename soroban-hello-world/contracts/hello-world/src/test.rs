@@ -0,0 +1,2473 @@
+#![cfg(test)]
+extern crate std;
+
+use super::*;
+use soroban_sdk::{
+    symbol_short,
+    testutils::{Address as _, AuthorizedFunction, AuthorizedInvocation, Events, Ledger},
+    token::{StellarAssetClient, TokenClient},
+    Address, BytesN, Env, IntoVal, String, Symbol, TryFromVal, Val, Vec,
+};
+
+// Release builds of this contract at schema versions 2 and 3, and a stand-in for a later release
+mod leasing_v2 {
+    soroban_sdk::contractimport!(file = "testdata/asset_leasing_v2.wasm");
+}
+mod leasing_v3 {
+    soroban_sdk::contractimport!(file = "testdata/asset_leasing_v3.wasm");
+}
+mod upgrade_probe {
+    soroban_sdk::contractimport!(file = "testdata/upgrade_probe.wasm");
+}
+
+const DAILY_RATE: i128 = 100;
+const DEPOSIT: i128 = 500;
+const STARTING_BALANCE: i128 = 10_000;
+const MIN_STAKE: i128 = 1_000;
+const SLASH_AMOUNT: i128 = 200;
+const VOTING_PERIOD: u64 = 3 * 24 * 60 * 60;
+const CLAIM_WINDOW: u64 = 2 * 24 * 60 * 60;
+const HOLDBACK: i128 = 300;
+
+struct Setup<'a> {
+    env: Env,
+    client: AssetLeasingContractClient<'a>,
+    token: TokenClient<'a>,
+    stake_token: TokenClient<'a>,
+    admin: Address,
+    arbitrators: Vec<Address>,
+    owner: Address,
+    lessee: Address,
+}
+
+fn setup<'a>() -> Setup<'a> {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let issuer = Address::generate(&env);
+    let sac = env.register_stellar_asset_contract_v2(issuer);
+    let token = TokenClient::new(&env, &sac.address());
+
+    let contract_id = env.register(AssetLeasingContract, ());
+    let client = AssetLeasingContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    client.initialize(&admin);
+
+    // Four staked arbitrators; each dispute draws a panel of three, two votes resolve it
+    let stake_sac = env.register_stellar_asset_contract_v2(Address::generate(&env));
+    let stake_token = TokenClient::new(&env, &stake_sac.address());
+    client.configure_arbitration(
+        &admin,
+        &ArbitrationConfig {
+            stake_token: stake_token.address.clone(),
+            min_stake: MIN_STAKE,
+            slash_amount: SLASH_AMOUNT,
+            panel_size: 3,
+            quorum: 2,
+            voting_period: VOTING_PERIOD,
+        },
+    );
+    let mut arbitrators = Vec::new(&env);
+    for _ in 0..4 {
+        let arbitrator = Address::generate(&env);
+        StellarAssetClient::new(&env, &stake_sac.address()).mint(&arbitrator, &MIN_STAKE);
+        client.grant_role(&admin, &Role::Arbitrator, &arbitrator);
+        client.join_arbitrator_pool(&arbitrator, &MIN_STAKE);
+        arbitrators.push_back(arbitrator);
+    }
+
+    let owner = Address::generate(&env);
+    let lessee = Address::generate(&env);
+    StellarAssetClient::new(&env, &sac.address()).mint(&lessee, &STARTING_BALANCE);
+
+    Setup { env, client, token, stake_token, admin, arbitrators, owner, lessee }
+}
+
+// 25% of the deposit for damages, 10% of the daily rate per day late, capped at the deposit
+fn default_policy() -> PenaltyPolicy {
+    PenaltyPolicy {
+        damage: DamagePenalty::Percentage(2_500),
+        late_fee_bps: 1_000,
+        grace_period: 0,
+        cap: DEPOSIT,
+    }
+}
+
+fn register_asset(s: &Setup) -> u64 {
+    register_asset_with_policy(s, &default_policy())
+}
+
+fn register_asset_with_policy(s: &Setup, policy: &PenaltyPolicy) -> u64 {
+    s.client.register_asset(
+        &s.owner,
+        &String::from_str(&s.env, "Excavator"),
+        &String::from_str(&s.env, "20t tracked excavator"),
+        &s.token.address,
+        &50_000,
+        &DAILY_RATE,
+        &1,
+        &30,
+        &DEPOSIT,
+        policy,
+    )
+}
+
+fn active_lease(s: &Setup, days: u64) -> u64 {
+    let asset_id = register_asset(s);
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &days, &PaymentSchedule::Upfront);
+    s.client.approve_lease(&lease_id, &s.owner);
+    lease_id
+}
+
+// Hand the asset back and have the owner accept it as-is
+fn return_clean(s: &Setup, lease_id: u64) {
+    s.client.return_asset(&lease_id, &s.lessee, &String::from_str(&s.env, "Clean"));
+    s.client.confirm_return(&lease_id, &s.owner, &0);
+}
+
+// Have the first quorum of the dispute panel vote the same way, resolving the dispute
+fn panel_rules(s: &Setup, lease_id: u64, in_favor_of_lessor: bool, penalty_percentage: u64) {
+    let panel = s.client.get_dispute_panel(&lease_id);
+    for arbitrator in panel.arbitrators.iter().take(panel.quorum as usize) {
+        s.client.cast_vote(&lease_id, &arbitrator, &in_favor_of_lessor, &penalty_percentage);
+    }
+}
+
+// Events published by the leasing contract itself (token transfers are skipped)
+fn contract_events(s: &Setup) -> Vec<(Vec<Val>, Val)> {
+    let mut events = Vec::new(&s.env);
+    for (contract, topics, data) in s.env.events().all().iter() {
+        if contract == s.client.address {
+            events.push_back((topics, data));
+        }
+    }
+    events
+}
+
+fn find_event(s: &Setup, category: Symbol, action: Symbol, id: u64) -> Option<Val> {
+    let topics: Vec<Val> = (category, action, id).into_val(&s.env);
+    contract_events(s).iter().find(|(t, _)| *t == topics).map(|(_, data)| data)
+}
+
+fn advance_to_end(s: &Setup, lease_id: u64) {
+    let end_time = s.client.get_lease(&lease_id).end_time;
+    s.env.ledger().set_timestamp(end_time);
+}
+
+#[test]
+fn create_lease_escrows_rent_and_deposit() {
+    let s = setup();
+    let asset_id = register_asset(&s);
+
+    s.client.create_lease(&asset_id, &s.lessee, &5, &PaymentSchedule::Upfront);
+
+    let escrowed = DAILY_RATE * 5 + DEPOSIT;
+    assert_eq!(s.token.balance(&s.client.address), escrowed);
+    assert_eq!(s.token.balance(&s.lessee), STARTING_BALANCE - escrowed);
+}
+
+#[test]
+fn approve_lease_locks_escrowed_value() {
+    let s = setup();
+    let asset_id = register_asset(&s);
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &5, &PaymentSchedule::Upfront);
+
+    s.client.approve_lease(&lease_id, &s.owner);
+
+    let stats = s.client.get_asset_stats();
+    assert_eq!(stats.active_leases, 1);
+    assert_eq!(stats.total_value_locked.get(s.token.address.clone()), Some(s.token.balance(&s.client.address)));
+}
+
+#[test]
+fn leases_use_the_asset_payment_token() {
+    let s = setup();
+    let usdc = s.env.register_stellar_asset_contract_v2(Address::generate(&s.env)).address();
+    StellarAssetClient::new(&s.env, &usdc).mint(&s.lessee, &STARTING_BALANCE);
+
+    let xlm_lease = active_lease(&s, 5);
+    let usdc_asset = s.client.register_asset(
+        &s.owner,
+        &String::from_str(&s.env, "Forklift"),
+        &String::from_str(&s.env, "Paid in USDC"),
+        &usdc,
+        &20_000,
+        &DAILY_RATE,
+        &1,
+        &30,
+        &DEPOSIT,
+        &default_policy(),
+    );
+    let usdc_lease = s.client.create_lease(&usdc_asset, &s.lessee, &2, &PaymentSchedule::Upfront);
+    s.client.approve_lease(&usdc_lease, &s.owner);
+
+    assert_eq!(s.client.get_lease(&usdc_lease).token, usdc);
+    let stats = s.client.get_asset_stats();
+    assert_eq!(stats.total_value_locked.get(s.token.address.clone()), Some(DAILY_RATE * 5 + DEPOSIT));
+    assert_eq!(stats.total_value_locked.get(usdc.clone()), Some(DAILY_RATE * 2 + DEPOSIT));
+
+    advance_to_end(&s, xlm_lease);
+    return_clean(&s, xlm_lease);
+    let stats = s.client.get_asset_stats();
+    assert_eq!(stats.total_earnings.get(s.token.address.clone()), Some(DAILY_RATE * 5));
+    assert_eq!(stats.total_earnings.get(usdc), None);
+}
+
+#[test]
+fn confirmed_return_pays_rent_and_refunds_deposit() {
+    let s = setup();
+    let lease_id = active_lease(&s, 5);
+
+    advance_to_end(&s, lease_id);
+    return_clean(&s, lease_id);
+
+    assert_eq!(s.token.balance(&s.owner), DAILY_RATE * 5);
+    assert_eq!(s.token.balance(&s.lessee), STARTING_BALANCE - DAILY_RATE * 5);
+    assert_eq!(s.token.balance(&s.client.address), 0);
+    assert_eq!(s.client.get_asset_stats().total_value_locked.get(s.token.address.clone()), Some(0));
+}
+
+#[test]
+fn accepted_damages_are_paid_from_deposit() {
+    let s = setup();
+    let lease_id = active_lease(&s, 5);
+
+    advance_to_end(&s, lease_id);
+    s.client.return_asset(&lease_id, &s.lessee, &String::from_str(&s.env, "Dented"));
+    let penalty = DEPOSIT / 4;
+    s.client.confirm_return(&lease_id, &s.owner, &penalty);
+    assert_eq!(s.client.get_lease_status(&lease_id), LeaseStatus::Returned);
+    s.client.accept_damages(&lease_id, &s.lessee);
+
+    assert_eq!(s.client.get_lease(&lease_id).penalty_amount, penalty);
+    assert_eq!(s.token.balance(&s.owner), DAILY_RATE * 5 + penalty);
+    assert_eq!(s.token.balance(&s.lessee), STARTING_BALANCE - DAILY_RATE * 5 - penalty);
+    assert_eq!(s.token.balance(&s.client.address), 0);
+}
+
+#[test]
+fn late_penalty_is_capped_at_deposit() {
+    let s = setup();
+    let lease_id = active_lease(&s, 5);
+
+    // 1000 days late at 10 per day would exceed the 500 deposit
+    let end_time = s.client.get_lease(&lease_id).end_time;
+    s.env.ledger().set_timestamp(end_time + 1000 * 24 * 60 * 60);
+    return_clean(&s, lease_id);
+
+    assert_eq!(s.client.get_lease(&lease_id).penalty_amount, DEPOSIT);
+    assert_eq!(s.token.balance(&s.owner), DAILY_RATE * 5 + DEPOSIT);
+    assert_eq!(s.token.balance(&s.client.address), 0);
+}
+
+#[test]
+fn panel_ruling_settles_escrow() {
+    let s = setup();
+    let lease_id = active_lease(&s, 5);
+
+    s.client.file_dispute(&lease_id, &s.owner, &String::from_str(&s.env, "Not returned"));
+    panel_rules(&s, lease_id, true, 40);
+
+    let penalty = DEPOSIT * 40 / 100;
+    assert_eq!(s.token.balance(&s.owner), DAILY_RATE * 5 + penalty);
+    assert_eq!(s.token.balance(&s.lessee), STARTING_BALANCE - DAILY_RATE * 5 - penalty);
+    assert_eq!(s.token.balance(&s.client.address), 0);
+}
+
+#[test]
+fn vote_rejects_penalty_above_maximum() {
+    let s = setup();
+    let lease_id = active_lease(&s, 5);
+
+    s.client.file_dispute(&lease_id, &s.owner, &String::from_str(&s.env, "Not returned"));
+    let arbitrator = s.client.get_dispute_panel(&lease_id).arbitrators.get_unchecked(0);
+    assert_eq!(s.client.try_cast_vote(&lease_id, &arbitrator, &true, &1_001), Err(Ok(LeasingError::InvalidPenaltyPercentage)));
+}
+
+#[test]
+fn weekly_schedule_escrows_first_installment_only() {
+    let s = setup();
+    let asset_id = register_asset(&s);
+
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &10, &PaymentSchedule::Weekly);
+
+    assert_eq!(s.token.balance(&s.client.address), DAILY_RATE * 7 + DEPOSIT);
+    let lease = s.client.get_lease(&lease_id);
+    assert_eq!(lease.amount_paid, DAILY_RATE * 7);
+    assert_eq!(s.client.next_due(&lease_id), Some(lease.start_time + 7 * 24 * 60 * 60));
+}
+
+#[test]
+fn pay_installment_charges_the_remaining_period() {
+    let s = setup();
+    let asset_id = register_asset(&s);
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &10, &PaymentSchedule::Weekly);
+    s.client.approve_lease(&lease_id, &s.owner);
+
+    s.client.pay_installment(&lease_id, &s.lessee);
+
+    // Second and last installment only covers the remaining 3 days
+    let lease = s.client.get_lease(&lease_id);
+    assert_eq!(lease.amount_paid, DAILY_RATE * 10);
+    assert_eq!(s.client.next_due(&lease_id), None);
+    let stats = s.client.get_asset_stats();
+    assert_eq!(stats.total_value_locked.get(s.token.address.clone()), Some(DAILY_RATE * 10 + DEPOSIT));
+}
+
+#[test]
+fn pay_installment_rejects_overpayment() {
+    let s = setup();
+    let lease_id = active_lease(&s, 5);
+    assert_eq!(s.client.try_pay_installment(&lease_id, &s.lessee), Err(Ok(LeasingError::InstallmentsPaid)));
+}
+
+#[test]
+fn missed_installments_are_detected() {
+    let s = setup();
+    let asset_id = register_asset(&s);
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &5, &PaymentSchedule::Daily);
+    s.client.approve_lease(&lease_id, &s.owner);
+    let start = s.client.get_lease(&lease_id).start_time;
+
+    assert_eq!(s.client.get_missed_installments(&lease_id), 0);
+    s.env.ledger().set_timestamp(start + 2 * 24 * 60 * 60 + 1);
+    assert_eq!(s.client.get_missed_installments(&lease_id), 2);
+
+    s.client.pay_installment(&lease_id, &s.lessee);
+    assert_eq!(s.client.get_missed_installments(&lease_id), 1);
+}
+
+#[test]
+fn installments_fall_due_from_approval() {
+    let s = setup();
+    let asset_id = register_asset(&s);
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &5, &PaymentSchedule::Daily);
+
+    // Nothing is missed while the proposal waits, nor right after a late approval
+    let approved_at = 3 * 24 * 60 * 60;
+    s.env.ledger().set_timestamp(approved_at);
+    assert_eq!(s.client.get_missed_installments(&lease_id), 0);
+    s.client.approve_lease(&lease_id, &s.owner);
+    assert_eq!(s.client.get_missed_installments(&lease_id), 0);
+    assert_eq!(s.client.next_due(&lease_id), Some(approved_at + 24 * 60 * 60));
+
+    s.env.ledger().set_timestamp(approved_at + 24 * 60 * 60 + 1);
+    assert_eq!(s.client.get_missed_installments(&lease_id), 1);
+}
+
+#[test]
+fn return_collects_outstanding_installments() {
+    let s = setup();
+    let asset_id = register_asset(&s);
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &5, &PaymentSchedule::Daily);
+    s.client.approve_lease(&lease_id, &s.owner);
+
+    advance_to_end(&s, lease_id);
+    return_clean(&s, lease_id);
+
+    assert_eq!(s.token.balance(&s.owner), DAILY_RATE * 5);
+    assert_eq!(s.token.balance(&s.client.address), 0);
+    assert_eq!(s.client.get_asset_stats().total_value_locked.get(s.token.address.clone()), Some(0));
+}
+
+#[test]
+fn streamed_rent_accrues_per_second() {
+    let s = setup();
+    let asset_id = register_asset(&s);
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &5, &PaymentSchedule::Stream);
+    s.client.approve_lease(&lease_id, &s.owner);
+    let start = s.client.get_lease(&lease_id).start_time;
+
+    // Half a day accrues half the daily rate
+    s.env.ledger().set_timestamp(start + 12 * 60 * 60);
+    assert_eq!(s.client.get_accrued(&lease_id), DAILY_RATE / 2);
+
+    // Accrual stops at end_time
+    s.env.ledger().set_timestamp(start + 50 * 24 * 60 * 60);
+    assert_eq!(s.client.get_accrued(&lease_id), DAILY_RATE * 5);
+}
+
+#[test]
+fn lessor_withdraws_accrued_rent() {
+    let s = setup();
+    let asset_id = register_asset(&s);
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &5, &PaymentSchedule::Stream);
+    s.client.approve_lease(&lease_id, &s.owner);
+    let start = s.client.get_lease(&lease_id).start_time;
+
+    s.env.ledger().set_timestamp(start + 2 * 24 * 60 * 60);
+    assert_eq!(s.client.withdraw_accrued(&lease_id), DAILY_RATE * 2);
+    assert_eq!(s.client.withdraw_accrued(&lease_id), 0);
+
+    assert_eq!(s.token.balance(&s.owner), DAILY_RATE * 2);
+    let stats = s.client.get_asset_stats();
+    assert_eq!(stats.total_value_locked.get(s.token.address.clone()), Some(DAILY_RATE * 3 + DEPOSIT));
+    assert_eq!(stats.total_earnings.get(s.token.address.clone()), Some(DAILY_RATE * 2));
+}
+
+#[test]
+fn streaming_starts_at_approval() {
+    let s = setup();
+    let asset_id = register_asset(&s);
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &5, &PaymentSchedule::Stream);
+
+    // Nothing accrues while the proposal waits for the owner
+    s.env.ledger().set_timestamp(4 * 24 * 60 * 60);
+    s.client.approve_lease(&lease_id, &s.owner);
+    assert_eq!(s.client.withdraw_accrued(&lease_id), 0);
+
+    let lease = s.client.get_lease(&lease_id);
+    assert_eq!(lease.start_time, 4 * 24 * 60 * 60);
+    assert_eq!(lease.end_time, 9 * 24 * 60 * 60);
+    assert_eq!(lease.last_payment, 4 * 24 * 60 * 60);
+
+    s.env.ledger().set_timestamp(5 * 24 * 60 * 60);
+    assert_eq!(s.client.withdraw_accrued(&lease_id), DAILY_RATE);
+}
+
+#[test]
+fn early_return_refunds_unstreamed_rent() {
+    let s = setup();
+    let asset_id = register_asset(&s);
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &5, &PaymentSchedule::Stream);
+    s.client.approve_lease(&lease_id, &s.owner);
+    let start = s.client.get_lease(&lease_id).start_time;
+
+    s.env.ledger().set_timestamp(start + 24 * 60 * 60);
+    s.client.withdraw_accrued(&lease_id);
+    s.env.ledger().set_timestamp(start + 3 * 24 * 60 * 60);
+    return_clean(&s, lease_id);
+
+    assert_eq!(s.token.balance(&s.owner), DAILY_RATE * 3);
+    assert_eq!(s.token.balance(&s.lessee), STARTING_BALANCE - DAILY_RATE * 3);
+    assert_eq!(s.token.balance(&s.client.address), 0);
+    let stats = s.client.get_asset_stats();
+    assert_eq!(stats.total_value_locked.get(s.token.address.clone()), Some(0));
+    assert_eq!(stats.total_earnings.get(s.token.address.clone()), Some(DAILY_RATE * 3));
+}
+
+#[test]
+fn withdraw_accrued_requires_stream_schedule() {
+    let s = setup();
+    let lease_id = active_lease(&s, 5);
+    assert_eq!(s.client.try_withdraw_accrued(&lease_id), Err(Ok(LeasingError::NotStreamed)));
+}
+
+#[test]
+fn early_termination_refunds_unused_days() {
+    let s = setup();
+    let lease_id = active_lease(&s, 10);
+    let start = s.client.get_lease(&lease_id).start_time;
+
+    // Two and a half days are billed as three
+    s.env.ledger().set_timestamp(start + 60 * 60 * 60);
+    return_clean(&s, lease_id);
+
+    assert_eq!(s.token.balance(&s.owner), DAILY_RATE * 3);
+    assert_eq!(s.token.balance(&s.lessee), STARTING_BALANCE - DAILY_RATE * 3);
+    assert_eq!(s.token.balance(&s.client.address), 0);
+}
+
+#[test]
+fn early_termination_charges_exit_fee() {
+    let s = setup();
+    let asset_id = register_asset(&s);
+    s.client.set_early_exit_fee(&asset_id, &s.owner, &150);
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &10, &PaymentSchedule::Upfront);
+    s.client.approve_lease(&lease_id, &s.owner);
+    let start = s.client.get_lease(&lease_id).start_time;
+
+    s.env.ledger().set_timestamp(start + 2 * 24 * 60 * 60);
+    return_clean(&s, lease_id);
+
+    assert_eq!(s.token.balance(&s.owner), DAILY_RATE * 2 + 150);
+    assert_eq!(s.token.balance(&s.lessee), STARTING_BALANCE - DAILY_RATE * 2 - 150);
+}
+
+#[test]
+fn early_exit_fee_never_exceeds_full_rent() {
+    let s = setup();
+    let asset_id = register_asset(&s);
+    s.client.set_early_exit_fee(&asset_id, &s.owner, &10_000);
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &3, &PaymentSchedule::Daily);
+    s.client.approve_lease(&lease_id, &s.owner);
+
+    return_clean(&s, lease_id);
+
+    assert_eq!(s.token.balance(&s.owner), DAILY_RATE * 3);
+    assert_eq!(s.token.balance(&s.client.address), 0);
+}
+
+#[test]
+fn approved_extension_moves_end_time() {
+    let s = setup();
+    let lease_id = active_lease(&s, 5);
+    let end_time = s.client.get_lease(&lease_id).end_time;
+
+    assert_eq!(s.client.request_extension(&lease_id, &s.lessee, &3), DAILY_RATE * 3);
+    s.client.approve_extension(&lease_id, &s.owner);
+
+    let lease = s.client.get_lease(&lease_id);
+    assert_eq!(lease.end_time, end_time + 3 * 24 * 60 * 60);
+    assert_eq!(lease.total_amount, DAILY_RATE * 8);
+    assert_eq!(lease.amount_paid, DAILY_RATE * 8);
+    let stats = s.client.get_asset_stats();
+    assert_eq!(stats.total_value_locked.get(s.token.address.clone()), Some(s.token.balance(&s.client.address)));
+}
+
+#[test]
+fn extension_can_use_current_rate() {
+    let s = setup();
+    let lease_id = active_lease(&s, 5);
+    let asset_id = s.client.get_lease(&lease_id).asset_id;
+    s.client.set_extension_pricing(&asset_id, &s.owner, &ExtensionPricing::Current);
+    s.client.update_asset(
+        &asset_id,
+        &s.owner,
+        &String::from_str(&s.env, "Excavator"),
+        &String::from_str(&s.env, "Rate increase"),
+        &(DAILY_RATE * 2),
+        &false,
+        &1,
+        &30,
+    );
+
+    assert_eq!(s.client.request_extension(&lease_id, &s.lessee, &2), DAILY_RATE * 4);
+}
+
+#[test]
+fn extension_respects_max_lease_days() {
+    let s = setup();
+    let lease_id = active_lease(&s, 25);
+    assert_eq!(s.client.try_request_extension(&lease_id, &s.lessee, &6), Err(Ok(LeasingError::InvalidExtension)));
+}
+
+// Register an asset with the given rate and lease-day limits, returning the contract result
+fn try_register_terms(s: &Setup, daily_rate: i128, min_lease_days: u64, max_lease_days: u64) -> Result<u64, LeasingError> {
+    s.client
+        .try_register_asset(
+            &s.owner,
+            &String::from_str(&s.env, "Excavator"),
+            &String::from_str(&s.env, "20t tracked excavator"),
+            &s.token.address,
+            &50_000,
+            &daily_rate,
+            &min_lease_days,
+            &max_lease_days,
+            &DEPOSIT,
+            &default_policy(),
+        )
+        .map(|id| id.unwrap())
+        .map_err(|err| err.unwrap())
+}
+
+#[test]
+fn register_asset_validates_lease_terms() {
+    let s = setup();
+    assert_eq!(try_register_terms(&s, DAILY_RATE, 0, 30), Err(LeasingError::InvalidAssetParameters));
+    assert_eq!(try_register_terms(&s, DAILY_RATE, 10, 5), Err(LeasingError::InvalidAssetParameters));
+    assert_eq!(try_register_terms(&s, 0, 1, 30), Err(LeasingError::InvalidAssetParameters));
+    assert_eq!(try_register_terms(&s, i128::MAX / 10, 1, 30), Err(LeasingError::ArithmeticOverflow));
+    assert_eq!(try_register_terms(&s, DAILY_RATE, 1, u64::MAX), Err(LeasingError::ArithmeticOverflow));
+    assert_eq!(try_register_terms(&s, DAILY_RATE, 5, 5), Ok(1));
+}
+
+#[test]
+fn update_asset_uses_the_same_validation() {
+    let s = setup();
+    let asset_id = register_asset(&s);
+    let update = |daily_rate: i128, min_lease_days: u64, max_lease_days: u64| {
+        s.client.try_update_asset(
+            &asset_id,
+            &s.owner,
+            &String::from_str(&s.env, "Excavator"),
+            &String::from_str(&s.env, "20t tracked excavator"),
+            &daily_rate,
+            &true,
+            &min_lease_days,
+            &max_lease_days,
+        )
+    };
+
+    assert_eq!(update(-1, 1, 30), Err(Ok(LeasingError::InvalidAssetParameters)));
+    assert_eq!(update(0, 1, 30), Err(Ok(LeasingError::InvalidAssetParameters)));
+    assert_eq!(update(DAILY_RATE, 30, 1), Err(Ok(LeasingError::InvalidAssetParameters)));
+    assert_eq!(update(i128::MAX, 1, 30), Err(Ok(LeasingError::ArithmeticOverflow)));
+    assert_eq!(s.client.get_asset(&asset_id).daily_rate, DAILY_RATE);
+}
+
+#[test]
+fn rejected_extension_is_refunded() {
+    let s = setup();
+    let lease_id = active_lease(&s, 5);
+
+    s.client.request_extension(&lease_id, &s.lessee, &3);
+    s.client.reject_extension(&lease_id, &s.owner);
+
+    assert_eq!(s.token.balance(&s.lessee), STARTING_BALANCE - DAILY_RATE * 5 - DEPOSIT);
+    assert_eq!(s.client.get_lease(&lease_id).pending_extension_days, 0);
+}
+
+#[test]
+fn overflowing_grace_period_is_a_typed_error() {
+    let s = setup();
+    let mut policy = default_policy();
+    policy.grace_period = u64::MAX;
+    let asset_id = register_asset_with_policy(&s, &policy);
+    assert_eq!(
+        s.client.try_create_lease(&asset_id, &s.lessee, &5, &PaymentSchedule::Upfront),
+        Err(Ok(LeasingError::ArithmeticOverflow))
+    );
+    assert_eq!(s.token.balance(&s.lessee), STARTING_BALANCE);
+}
+
+#[test]
+fn settled_extension_cannot_be_refunded_again() {
+    let s = setup();
+    let other_lease = active_lease(&s, 5);
+    let lease_id = active_lease(&s, 5);
+    s.client.request_extension(&lease_id, &s.lessee, &5);
+    return_clean(&s, lease_id);
+
+    // Settlement refunded the extension rent and cleared the request
+    let lease = s.client.get_lease(&lease_id);
+    assert_eq!(lease.pending_extension_days, 0);
+    assert_eq!(lease.pending_extension_amount, 0);
+    assert_eq!(s.client.try_reject_extension(&lease_id, &s.owner), Err(Ok(LeasingError::NoExtensionPending)));
+
+    // The other lease's escrow is untouched
+    let other = s.client.get_lease(&other_lease);
+    assert_eq!(s.token.balance(&s.client.address), other.amount_paid + other.security_deposit);
+    assert_consistent(&s);
+}
+
+#[test]
+fn reject_extension_requires_a_live_lease() {
+    let s = setup();
+    let lease_id = active_lease(&s, 5);
+    s.client.request_extension(&lease_id, &s.lessee, &2);
+    s.client.file_dispute(&lease_id, &s.lessee, &String::from_str(&s.env, "Broken on delivery"));
+    assert_eq!(s.client.try_reject_extension(&lease_id, &s.owner), Err(Ok(LeasingError::LeaseNotActive)));
+}
+
+#[test]
+fn auto_renew_charges_allowance_for_next_term() {
+    let s = setup();
+    let lease_id = active_lease(&s, 5);
+    s.client.set_auto_renew(&lease_id, &s.lessee, &7);
+    s.token.approve(&s.lessee, &s.client.address, &(DAILY_RATE * 7), &1000);
+    let end_time = s.client.get_lease(&lease_id).end_time;
+
+    s.env.ledger().set_timestamp(end_time);
+    assert_eq!(s.client.renew_lease(&lease_id), DAILY_RATE * 7);
+
+    let lease = s.client.get_lease(&lease_id);
+    assert_eq!(lease.end_time, end_time + 7 * 24 * 60 * 60);
+    assert_eq!(lease.total_amount, DAILY_RATE * 12);
+    assert_eq!(s.token.balance(&s.client.address), DAILY_RATE * 12 + DEPOSIT);
+}
+
+#[test]
+fn renew_lease_waits_for_end_of_term() {
+    let s = setup();
+    let lease_id = active_lease(&s, 5);
+    s.client.set_auto_renew(&lease_id, &s.lessee, &7);
+    assert_eq!(s.client.try_renew_lease(&lease_id), Err(Ok(LeasingError::LeaseTermNotEnded)));
+}
+
+#[test]
+fn lessor_can_reject_pending_lease() {
+    let s = setup();
+    let asset_id = register_asset(&s);
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &5, &PaymentSchedule::Upfront);
+
+    s.client.reject_lease(&lease_id, &s.owner);
+
+    assert_eq!(s.client.get_lease(&lease_id).status, LeaseStatus::Canceled);
+    assert!(s.client.get_asset(&asset_id).available);
+    assert_eq!(s.token.balance(&s.lessee), STARTING_BALANCE);
+    assert_eq!(s.token.balance(&s.client.address), 0);
+    let lessor = s.client.get_user_portfolio(&s.owner);
+    let lessee = s.client.get_user_portfolio(&s.lessee);
+    assert!(lessor.active_leases_as_lessor.is_empty());
+    assert!(lessee.active_leases_as_lessee.is_empty());
+    assert!(lessee.completed_leases.is_empty());
+}
+
+#[test]
+fn lessee_can_cancel_pending_lease() {
+    let s = setup();
+    let asset_id = register_asset(&s);
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &10, &PaymentSchedule::Daily);
+    s.client.pay_installment(&lease_id, &s.lessee);
+
+    s.client.cancel_lease(&lease_id, &s.lessee);
+
+    assert_eq!(s.client.get_lease(&lease_id).status, LeaseStatus::Canceled);
+    assert_eq!(s.token.balance(&s.lessee), STARTING_BALANCE);
+    // Asset can be leased again
+    s.client.create_lease(&asset_id, &s.lessee, &2, &PaymentSchedule::Upfront);
+}
+
+#[test]
+fn active_lease_cannot_be_canceled() {
+    let s = setup();
+    let lease_id = active_lease(&s, 5);
+    assert_eq!(s.client.try_cancel_lease(&lease_id, &s.lessee), Err(Ok(LeasingError::InvalidStatusTransition)));
+}
+
+#[test]
+fn canceled_lease_cannot_be_approved() {
+    let s = setup();
+    let asset_id = register_asset(&s);
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &5, &PaymentSchedule::Upfront);
+    s.client.cancel_lease(&lease_id, &s.lessee);
+    assert_eq!(s.client.try_approve_lease(&lease_id, &s.owner), Err(Ok(LeasingError::InvalidStatusTransition)));
+}
+
+#[test]
+fn lease_status_follows_lifecycle() {
+    let s = setup();
+    let asset_id = register_asset(&s);
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &5, &PaymentSchedule::Upfront);
+    assert_eq!(s.client.get_lease_status(&lease_id), LeaseStatus::Pending);
+
+    s.client.approve_lease(&lease_id, &s.owner);
+    assert_eq!(s.client.get_lease_status(&lease_id), LeaseStatus::Active);
+
+    s.client.file_dispute(&lease_id, &s.lessee, &String::from_str(&s.env, "Broken on delivery"));
+    assert_eq!(s.client.get_lease_status(&lease_id), LeaseStatus::Disputed);
+
+    panel_rules(&s, lease_id, false, 0);
+    assert_eq!(s.client.get_lease_status(&lease_id), LeaseStatus::Completed);
+}
+
+#[test]
+fn completed_lease_cannot_be_approved_again() {
+    let s = setup();
+    let lease_id = active_lease(&s, 5);
+    return_clean(&s, lease_id);
+    assert_eq!(s.client.try_approve_lease(&lease_id, &s.owner), Err(Ok(LeasingError::InvalidStatusTransition)));
+}
+
+#[test]
+fn disputed_lease_cannot_be_disputed_twice() {
+    let s = setup();
+    let lease_id = active_lease(&s, 5);
+    s.client.file_dispute(&lease_id, &s.lessee, &String::from_str(&s.env, "Broken"));
+    assert_eq!(s.client.try_file_dispute(&lease_id, &s.owner, &String::from_str(&s.env, "Late")), Err(Ok(LeasingError::InvalidStatusTransition)));
+}
+
+#[test]
+fn errors_have_stable_codes() {
+    let s = setup();
+    let asset_id = register_asset(&s);
+
+    assert_eq!(s.client.try_get_asset(&99), Err(Ok(LeasingError::AssetNotFound)));
+    assert_eq!(s.client.try_get_lease(&99), Err(Ok(LeasingError::LeaseNotFound)));
+    assert_eq!(
+        s.client.try_create_lease(&asset_id, &s.lessee, &31, &PaymentSchedule::Upfront),
+        Err(Ok(LeasingError::InvalidLeaseDuration))
+    );
+
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &5, &PaymentSchedule::Upfront);
+    assert_eq!(
+        s.client.try_create_lease(&asset_id, &s.lessee, &5, &PaymentSchedule::Upfront),
+        Err(Ok(LeasingError::AssetNotAvailable))
+    );
+    assert_eq!(s.client.try_approve_lease(&lease_id, &s.lessee), Err(Ok(LeasingError::NotAssetOwner)));
+    assert_eq!(LeasingError::NotAssetOwner as u32, 6);
+}
+
+#[test]
+fn asset_events_carry_the_asset() {
+    let s = setup();
+    let asset_id = register_asset(&s);
+
+    let data = find_event(&s, symbol_short!("asset"), symbol_short!("created"), asset_id).unwrap();
+    assert_eq!(Asset::try_from_val(&s.env, &data).unwrap(), s.client.get_asset(&asset_id));
+
+    s.client.set_early_exit_fee(&asset_id, &s.owner, &25);
+    let data = find_event(&s, symbol_short!("asset"), symbol_short!("updated"), asset_id).unwrap();
+    assert_eq!(Asset::try_from_val(&s.env, &data).unwrap().early_exit_fee, 25);
+}
+
+#[test]
+fn lease_lifecycle_publishes_events() {
+    let s = setup();
+    let asset_id = register_asset(&s);
+
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &5, &PaymentSchedule::Upfront);
+    let data = find_event(&s, symbol_short!("lease"), symbol_short!("created"), lease_id).unwrap();
+    assert_eq!(Lease::try_from_val(&s.env, &data).unwrap().status, LeaseStatus::Pending);
+
+    s.client.approve_lease(&lease_id, &s.owner);
+    assert!(find_event(&s, symbol_short!("lease"), symbol_short!("approved"), lease_id).is_some());
+
+    s.client.file_dispute(&lease_id, &s.owner, &String::from_str(&s.env, "Late"));
+    assert!(find_event(&s, symbol_short!("dispute"), symbol_short!("filed"), lease_id).is_some());
+
+    panel_rules(&s, lease_id, true, 10);
+    let data = find_event(&s, symbol_short!("dispute"), symbol_short!("resolved"), lease_id).unwrap();
+    let lease = Lease::try_from_val(&s.env, &data).unwrap();
+    assert_eq!(lease.status, LeaseStatus::Completed);
+    assert_eq!(lease.penalty_amount, DEPOSIT / 10);
+}
+
+#[test]
+fn cancel_and_complete_publish_events() {
+    let s = setup();
+    let asset_id = register_asset(&s);
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &5, &PaymentSchedule::Upfront);
+    s.client.cancel_lease(&lease_id, &s.lessee);
+    assert!(find_event(&s, symbol_short!("lease"), symbol_short!("canceled"), lease_id).is_some());
+
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &5, &PaymentSchedule::Upfront);
+    s.client.approve_lease(&lease_id, &s.owner);
+    return_clean(&s, lease_id);
+    assert!(find_event(&s, symbol_short!("lease"), symbol_short!("completed"), lease_id).is_some());
+}
+
+#[test]
+fn stats_changes_are_published() {
+    let s = setup();
+    active_lease(&s, 5);
+
+    let topics: Vec<Val> = (symbol_short!("stats"), symbol_short!("updated")).into_val(&s.env);
+    let (_, data) = contract_events(&s).iter().filter(|(t, _)| *t == topics).last().unwrap();
+    let stats = AssetStats::try_from_val(&s.env, &data).unwrap();
+    assert_eq!(stats.active_leases, 1);
+    assert_eq!(stats.total_value_locked.get(s.token.address.clone()), Some(DAILY_RATE * 5 + DEPOSIT));
+}
+
+#[test]
+fn initialize_only_once() {
+    let s = setup();
+    assert_eq!(s.client.get_admin(), s.admin);
+    assert_eq!(s.client.try_initialize(&s.owner), Err(Ok(LeasingError::AlreadyInitialized)));
+}
+
+#[test]
+fn only_panel_members_can_vote() {
+    let s = setup();
+    let lease_id = active_lease(&s, 5);
+    s.client.file_dispute(&lease_id, &s.owner, &String::from_str(&s.env, "Not returned"));
+
+    let panel = s.client.get_dispute_panel(&lease_id);
+    assert_eq!(panel.arbitrators.len(), 3);
+    assert_eq!(panel.deadline, VOTING_PERIOD);
+    for arbitrator in panel.arbitrators.iter() {
+        assert_eq!(s.client.get_arbitrator(&arbitrator).open_panels, 1);
+    }
+
+    // The pool member left off the panel, and the admin, have no say
+    let outsider = s.arbitrators.iter().find(|a| !panel.arbitrators.contains(a)).unwrap();
+    assert_eq!(s.client.try_cast_vote(&lease_id, &outsider, &true, &100), Err(Ok(LeasingError::NotPanelMember)));
+    assert_eq!(s.client.try_cast_vote(&lease_id, &s.admin, &true, &100), Err(Ok(LeasingError::NotPanelMember)));
+
+    let member = panel.arbitrators.get_unchecked(0);
+    s.client.cast_vote(&lease_id, &member, &true, &100);
+    assert_eq!(s.client.try_cast_vote(&lease_id, &member, &false, &0), Err(Ok(LeasingError::AlreadyVoted)));
+    assert_eq!(s.client.get_lease_status(&lease_id), LeaseStatus::Disputed);
+}
+
+#[test]
+fn majority_decides_and_averages_lessor_penalties() {
+    let s = setup();
+    let lease_id = active_lease(&s, 5);
+    advance_to_end(&s, lease_id);
+    s.client.file_dispute(&lease_id, &s.owner, &String::from_str(&s.env, "Scratched"));
+
+    let panel = s.client.get_dispute_panel(&lease_id);
+    s.client.cast_vote(&lease_id, &panel.arbitrators.get_unchecked(0), &true, &20);
+    s.client.cast_vote(&lease_id, &panel.arbitrators.get_unchecked(1), &true, &60);
+
+    let lease = s.client.get_lease(&lease_id);
+    assert_eq!(lease.status, LeaseStatus::Completed);
+    assert_eq!(lease.penalty_amount, DEPOSIT * 40 / 100);
+}
+
+#[test]
+fn split_panel_favors_lessee() {
+    let s = setup();
+    let lease_id = active_lease(&s, 5);
+    s.client.file_dispute(&lease_id, &s.owner, &String::from_str(&s.env, "Scratched"));
+
+    // One vote each way reaches quorum with a tie, which goes to the lessee
+    let panel = s.client.get_dispute_panel(&lease_id);
+    s.client.cast_vote(&lease_id, &panel.arbitrators.get_unchecked(0), &true, &100);
+    s.client.cast_vote(&lease_id, &panel.arbitrators.get_unchecked(1), &false, &0);
+    assert_eq!(s.client.get_lease_status(&lease_id), LeaseStatus::Completed);
+    assert_eq!(s.client.get_lease(&lease_id).penalty_amount, 0);
+}
+
+#[test]
+fn deadline_resolution_slashes_non_voters() {
+    let s = setup();
+    let lease_id = active_lease(&s, 5);
+    s.client.file_dispute(&lease_id, &s.lessee, &String::from_str(&s.env, "Broken on delivery"));
+
+    let panel = s.client.get_dispute_panel(&lease_id);
+    let voter = panel.arbitrators.get_unchecked(0);
+    s.client.cast_vote(&lease_id, &voter, &false, &0);
+    assert_eq!(s.client.try_finalize_dispute(&lease_id), Err(Ok(LeasingError::VotingOpen)));
+
+    s.env.ledger().set_timestamp(VOTING_PERIOD + 1);
+    assert_eq!(
+        s.client.try_cast_vote(&lease_id, &panel.arbitrators.get_unchecked(1), &true, &0),
+        Err(Ok(LeasingError::VotingClosed))
+    );
+    s.client.finalize_dispute(&lease_id);
+    assert_eq!(s.client.get_lease_status(&lease_id), LeaseStatus::Completed);
+
+    // Both absentees lose their slash, which goes to the one voter
+    assert_eq!(s.client.get_arbitrator(&voter), ArbitratorInfo { stake: MIN_STAKE + 2 * SLASH_AMOUNT, open_panels: 0 });
+    for absentee in panel.arbitrators.slice(1..3).iter() {
+        assert_eq!(s.client.get_arbitrator(&absentee).stake, MIN_STAKE - SLASH_AMOUNT);
+        assert!(!s.client.get_arbitrator_pool().contains(&absentee));
+    }
+    assert_eq!(s.client.try_finalize_dispute(&lease_id), Err(Ok(LeasingError::LeaseClosed)));
+}
+
+#[test]
+fn arbitrators_withdraw_stake_when_free() {
+    let s = setup();
+    let lease_id = active_lease(&s, 5);
+    s.client.file_dispute(&lease_id, &s.owner, &String::from_str(&s.env, "Not returned"));
+
+    let panel = s.client.get_dispute_panel(&lease_id);
+    let member = panel.arbitrators.get_unchecked(0);
+    assert_eq!(s.client.try_leave_arbitrator_pool(&member), Err(Ok(LeasingError::ArbitratorBusy)));
+
+    panel_rules(&s, lease_id, false, 0);
+    assert_eq!(s.client.leave_arbitrator_pool(&member), MIN_STAKE);
+    assert_eq!(s.stake_token.balance(&member), MIN_STAKE);
+    assert!(!s.client.get_arbitrator_pool().contains(&member));
+}
+
+#[test]
+fn stake_token_is_locked_while_stakes_are_held() {
+    let s = setup();
+    let mut config = s.client.get_arbitration_config();
+    config.stake_token = s.token.address.clone();
+    assert_eq!(s.client.try_configure_arbitration(&s.admin, &config), Err(Ok(LeasingError::InvalidArbitrationConfig)));
+
+    // Other settings can still change
+    let mut current = s.client.get_arbitration_config();
+    current.voting_period = VOTING_PERIOD * 2;
+    s.client.configure_arbitration(&s.admin, &current);
+
+    for arbitrator in s.arbitrators.iter() {
+        s.client.leave_arbitrator_pool(&arbitrator);
+    }
+    s.client.configure_arbitration(&s.admin, &config);
+    assert_eq!(s.client.get_arbitration_config().stake_token, s.token.address);
+}
+
+#[test]
+fn disputes_need_a_full_panel() {
+    let s = setup();
+    let lease_id = active_lease(&s, 5);
+    s.client.revoke_role(&s.admin, &Role::Arbitrator, &s.arbitrators.get_unchecked(0));
+    s.client.revoke_role(&s.admin, &Role::Arbitrator, &s.arbitrators.get_unchecked(1));
+
+    assert_eq!(
+        s.client.try_file_dispute(&lease_id, &s.owner, &String::from_str(&s.env, "Not returned")),
+        Err(Ok(LeasingError::InsufficientArbitrators))
+    );
+}
+
+#[test]
+fn only_arbitrators_can_join_pool() {
+    let s = setup();
+    let outsider = Address::generate(&s.env);
+    assert_eq!(s.client.try_join_arbitrator_pool(&outsider, &MIN_STAKE), Err(Ok(LeasingError::Unauthorized)));
+    assert_eq!(s.client.get_arbitrator_pool().len(), 4);
+}
+
+#[test]
+fn admin_grants_and_revokes_roles() {
+    let s = setup();
+    let pauser = Address::generate(&s.env);
+
+    s.client.grant_role(&s.admin, &Role::Pauser, &pauser);
+    assert!(s.client.has_role(&Role::Pauser, &pauser));
+    assert!(!s.client.has_role(&Role::Arbitrator, &pauser));
+
+    s.client.revoke_role(&s.admin, &Role::Pauser, &pauser);
+    assert!(!s.client.has_role(&Role::Pauser, &pauser));
+
+    assert_eq!(s.client.try_grant_role(&s.admin, &Role::Admin, &pauser), Err(Ok(LeasingError::InvalidRole)));
+    assert_eq!(s.client.try_grant_role(&s.owner, &Role::Pauser, &pauser), Err(Ok(LeasingError::Unauthorized)));
+}
+
+#[test]
+fn admin_handover_takes_two_steps() {
+    let s = setup();
+    let new_admin = Address::generate(&s.env);
+
+    s.client.transfer_admin(&s.admin, &new_admin);
+    assert_eq!(s.client.get_admin(), s.admin);
+    assert_eq!(s.client.try_accept_admin(&s.owner), Err(Ok(LeasingError::NoPendingAdmin)));
+
+    s.client.accept_admin(&new_admin);
+    assert_eq!(s.client.get_admin(), new_admin);
+    assert!(s.client.has_role(&Role::Admin, &new_admin));
+    assert!(!s.client.has_role(&Role::Admin, &s.admin));
+    assert_eq!(s.client.try_accept_admin(&new_admin), Err(Ok(LeasingError::NoPendingAdmin)));
+}
+
+#[test]
+fn records_live_in_persistent_storage() {
+    let s = setup();
+    let lease_id = active_lease(&s, 5);
+
+    s.env.as_contract(&s.client.address, || {
+        let storage = s.env.storage();
+        assert!(storage.persistent().has(&AssetRegistry::Asset(1)));
+        assert!(storage.persistent().has(&LeaseRegistry::Lease(lease_id)));
+        assert!(storage.persistent().has(&UserRegistry::User(s.lessee.clone())));
+        assert!(!storage.instance().has(&AssetRegistry::Asset(1)));
+        assert!(!storage.instance().has(&LeaseRegistry::Lease(lease_id)));
+    });
+}
+
+#[test]
+fn migrate_moves_legacy_instance_entries() {
+    let s = setup();
+    let lease_id = active_lease(&s, 5);
+    let asset = s.client.get_asset(&1);
+    let lease = s.client.get_lease(&lease_id);
+    let portfolio = s.client.get_user_portfolio(&s.lessee);
+
+    // Recreate the pre-migration layout: everything in instance storage
+    s.env.as_contract(&s.client.address, || {
+        let storage = s.env.storage();
+        storage.persistent().remove(&AssetRegistry::Asset(1));
+        storage.persistent().remove(&LeaseRegistry::Lease(lease_id));
+        storage.persistent().remove(&UserRegistry::User(s.lessee.clone()));
+        storage.instance().set(&AssetRegistry::Asset(1), &asset);
+        storage.instance().set(&LeaseRegistry::Lease(lease_id), &lease);
+        storage.instance().set(&UserRegistry::User(s.lessee.clone()), &portfolio);
+    });
+
+    // Legacy entries stay readable until moved
+    assert_eq!(s.client.get_lease(&lease_id), lease);
+
+    assert!(!s.client.migrate(&s.admin, &s.token.address, &1));
+    assert!(s.client.migrate(&s.admin, &s.token.address, &10));
+
+    s.env.as_contract(&s.client.address, || {
+        let storage = s.env.storage();
+        assert!(storage.persistent().has(&AssetRegistry::Asset(1)));
+        assert!(storage.persistent().has(&LeaseRegistry::Lease(lease_id)));
+        assert!(storage.persistent().has(&UserRegistry::User(s.lessee.clone())));
+        assert!(!storage.instance().has(&AssetRegistry::Asset(1)));
+        assert!(!storage.instance().has(&LeaseRegistry::Lease(lease_id)));
+        assert!(!storage.instance().has(&UserRegistry::User(s.lessee.clone())));
+    });
+    assert_eq!(s.client.get_asset(&1), asset);
+    assert_eq!(s.client.get_lease(&lease_id), lease);
+}
+
+#[test]
+fn migrate_moves_roles_and_pool_out_of_instance() {
+    let s = setup();
+    let pauser = Address::generate(&s.env);
+    let pool = s.client.get_arbitrator_pool();
+    assert_eq!(pool, s.arbitrators);
+
+    // Recreate the version 2 layout: pool and role grants in instance storage
+    s.env.as_contract(&s.client.address, || {
+        let storage = s.env.storage();
+        storage.persistent().remove(&ArbitrationRegistry::Pool);
+        storage.instance().set(&symbol_short!("ARB_POOL"), &pool);
+        for arbitrator in pool.iter() {
+            storage.persistent().remove(&RoleRegistry::Member(Role::Arbitrator, arbitrator.clone()));
+            storage.instance().set(&RoleRegistry::Member(Role::Arbitrator, arbitrator), &true);
+        }
+        storage.instance().set(&RoleRegistry::Member(Role::Pauser, pauser.clone()), &true);
+        storage.instance().set(&symbol_short!("SCHEMA"), &2u32);
+    });
+
+    // Legacy entries stay readable until moved
+    assert_eq!(s.client.get_arbitrator_pool(), pool);
+    assert!(s.client.has_role(&Role::Arbitrator, &pool.get(0).unwrap()));
+    assert!(s.client.has_role(&Role::Pauser, &pauser));
+
+    assert!(s.client.migrate(&s.admin, &s.token.address, &10));
+    assert_eq!(s.client.get_schema_version(), 3);
+    s.env.as_contract(&s.client.address, || {
+        let storage = s.env.storage();
+        assert!(storage.persistent().has(&ArbitrationRegistry::Pool));
+        assert!(!storage.instance().has(&symbol_short!("ARB_POOL")));
+        for arbitrator in pool.iter() {
+            assert!(storage.persistent().has(&RoleRegistry::Member(Role::Arbitrator, arbitrator.clone())));
+            assert!(!storage.instance().has(&RoleRegistry::Member(Role::Arbitrator, arbitrator)));
+        }
+    });
+    assert_eq!(s.client.get_arbitrator_pool(), pool);
+
+    // Grants outside the pool are read from instance storage until touched again
+    assert!(s.client.has_role(&Role::Pauser, &pauser));
+    s.client.revoke_role(&s.admin, &Role::Pauser, &pauser);
+    assert!(!s.client.has_role(&Role::Pauser, &pauser));
+}
+
+#[test]
+fn migrate_is_admin_only() {
+    let s = setup();
+    assert_eq!(s.client.try_migrate(&s.owner, &s.token.address, &10), Err(Ok(LeasingError::Unauthorized)));
+}
+
+#[test]
+fn user_assets_are_paginated() {
+    let s = setup();
+    for _ in 0..5 {
+        register_asset(&s);
+    }
+
+    let page = s.client.get_user_assets(&s.owner, &0, &2);
+    assert_eq!(page.items.len(), 2);
+    assert_eq!(page.items.get_unchecked(0).asset_id, 1);
+    assert_eq!(page.next_cursor, Some(2));
+
+    let page = s.client.get_user_assets(&s.owner, &4, &2);
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.items.get_unchecked(0).asset_id, 5);
+    assert_eq!(page.next_cursor, None);
+}
+
+#[test]
+fn zero_page_limit_still_advances() {
+    let s = setup();
+    active_lease(&s, 5);
+    register_asset(&s);
+
+    let page = s.client.get_user_assets(&s.owner, &0, &0);
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.next_cursor, Some(1));
+
+    let page = s.client.get_user_active_leases(&s.lessee, &0, &0);
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.next_cursor, None);
+
+    let page = s.client.get_available_assets(&1, &0);
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.items.get_unchecked(0).asset_id, 2);
+    assert_eq!(page.next_cursor, None);
+
+    let page = s.client.get_leases_by_status(&LeaseStatus::Completed, &1, &0);
+    assert!(page.items.is_empty());
+    assert_eq!(page.next_cursor, None);
+}
+
+#[test]
+fn user_active_leases_are_paginated() {
+    let s = setup();
+    let first = active_lease(&s, 5);
+    let second = active_lease(&s, 5);
+    let third = active_lease(&s, 5);
+
+    let page = s.client.get_user_active_leases(&s.lessee, &0, &2);
+    assert_eq!(page.items.len(), 2);
+    assert_eq!(page.items.get_unchecked(0).lease_id, first);
+    assert_eq!(page.items.get_unchecked(1).lease_id, second);
+
+    let page = s.client.get_user_active_leases(&s.lessee, &page.next_cursor.unwrap(), &2);
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.items.get_unchecked(0).lease_id, third);
+    assert_eq!(page.next_cursor, None);
+}
+
+#[test]
+fn available_assets_skip_leased_ones() {
+    let s = setup();
+    active_lease(&s, 5);
+    register_asset(&s);
+    register_asset(&s);
+
+    let page = s.client.get_available_assets(&0, &10);
+    assert_eq!(page.items.len(), 2);
+    assert_eq!(page.items.get_unchecked(0).asset_id, 2);
+    assert_eq!(page.next_cursor, None);
+
+    let page = s.client.get_available_assets(&0, &1);
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.next_cursor, Some(3));
+}
+
+#[test]
+fn leases_can_be_listed_by_status() {
+    let s = setup();
+    let active = active_lease(&s, 5);
+    let asset_id = register_asset(&s);
+    let pending = s.client.create_lease(&asset_id, &s.lessee, &5, &PaymentSchedule::Upfront);
+
+    let page = s.client.get_leases_by_status(&LeaseStatus::Pending, &1, &10);
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.items.get_unchecked(0).lease_id, pending);
+
+    let page = s.client.get_leases_by_status(&LeaseStatus::Active, &1, &10);
+    assert_eq!(page.items.get_unchecked(0).lease_id, active);
+    assert!(s.client.get_leases_by_status(&LeaseStatus::Disputed, &1, &10).items.is_empty());
+}
+
+#[test]
+fn dispute_record_keeps_return_notes_intact() {
+    let s = setup();
+    let lease_id = active_lease(&s, 5);
+    s.env.ledger().set_timestamp(100);
+    s.client.file_dispute(&lease_id, &s.owner, &String::from_str(&s.env, "Not returned"));
+
+    let dispute = s.client.get_dispute(&lease_id);
+    assert_eq!(dispute.filer, s.owner);
+    assert_eq!(dispute.reason, String::from_str(&s.env, "Not returned"));
+    assert_eq!(dispute.filed_at, 100);
+    assert_eq!(dispute.resolved_at, None);
+    assert_eq!(s.client.get_lease(&lease_id).return_condition, String::from_str(&s.env, ""));
+
+    s.env.ledger().set_timestamp(200);
+    panel_rules(&s, lease_id, false, 0);
+    assert_eq!(s.client.get_dispute(&lease_id).resolved_at, Some(200));
+}
+
+#[test]
+fn parties_submit_evidence_until_dispute_closes() {
+    let s = setup();
+    let lease_id = active_lease(&s, 5);
+    assert_eq!(
+        s.client.try_submit_evidence(&lease_id, &s.owner, &BytesN::from_array(&s.env, &[0; 32]), &String::from_str(&s.env, "ipfs://none")),
+        Err(Ok(LeasingError::DisputeNotFound))
+    );
+    s.client.file_dispute(&lease_id, &s.lessee, &String::from_str(&s.env, "Broken on delivery"));
+
+    let photo = BytesN::from_array(&s.env, &[1; 32]);
+    let invoice = BytesN::from_array(&s.env, &[2; 32]);
+    assert_eq!(s.client.submit_evidence(&lease_id, &s.lessee, &photo, &String::from_str(&s.env, "ipfs://photo")), 1);
+    assert_eq!(s.client.submit_evidence(&lease_id, &s.owner, &invoice, &String::from_str(&s.env, "ipfs://invoice")), 2);
+
+    let outsider = Address::generate(&s.env);
+    assert_eq!(
+        s.client.try_submit_evidence(&lease_id, &outsider, &photo, &String::from_str(&s.env, "ipfs://spam")),
+        Err(Ok(LeasingError::NotLeaseParty))
+    );
+
+    let evidence = s.client.get_dispute(&lease_id).evidence;
+    assert_eq!(evidence.len(), 2);
+    assert_eq!(evidence.get_unchecked(0).submitter, s.lessee);
+    assert_eq!(evidence.get_unchecked(1).content_hash, invoice);
+
+    panel_rules(&s, lease_id, false, 0);
+    assert_eq!(
+        s.client.try_submit_evidence(&lease_id, &s.owner, &invoice, &String::from_str(&s.env, "ipfs://late")),
+        Err(Ok(LeasingError::LeaseClosed))
+    );
+}
+
+#[test]
+fn lessee_ruling_refunds_unused_rent_and_deposit() {
+    let s = setup();
+    let lease_id = active_lease(&s, 10);
+
+    // Disputed two days in; the lessee pays only for those two days
+    s.env.ledger().set_timestamp(2 * 24 * 60 * 60);
+    s.client.file_dispute(&lease_id, &s.lessee, &String::from_str(&s.env, "Broken on delivery"));
+    s.env.ledger().set_timestamp(5 * 24 * 60 * 60);
+    panel_rules(&s, lease_id, false, 0);
+
+    assert_eq!(s.token.balance(&s.owner), DAILY_RATE * 2);
+    assert_eq!(s.token.balance(&s.lessee), STARTING_BALANCE - DAILY_RATE * 2);
+    assert_eq!(s.token.balance(&s.client.address), 0);
+    assert_eq!(s.client.get_asset_stats().total_value_locked.get(s.token.address.clone()), Some(0));
+}
+
+#[test]
+fn lessor_award_beyond_escrow_becomes_debt() {
+    let s = setup();
+    let lease_id = active_lease(&s, 5);
+    advance_to_end(&s, lease_id);
+    s.client.file_dispute(&lease_id, &s.owner, &String::from_str(&s.env, "Engine seized"));
+    panel_rules(&s, lease_id, true, 300);
+
+    // The deposit covers the first 100% of the award, the rest is owed
+    assert_eq!(s.client.get_lease(&lease_id).penalty_amount, DEPOSIT);
+    assert_eq!(s.token.balance(&s.owner), DAILY_RATE * 5 + DEPOSIT);
+    assert_eq!(s.token.balance(&s.client.address), 0);
+    let debt = s.client.get_debt(&lease_id);
+    assert_eq!(debt.debtor, s.lessee);
+    assert_eq!(debt.creditor, s.owner);
+    assert_eq!(debt.amount, DEPOSIT * 2);
+
+    assert_eq!(s.client.repay_debt(&lease_id, &s.lessee, &400), DEPOSIT * 2 - 400);
+    assert_eq!(s.client.repay_debt(&lease_id, &s.lessee, &10_000), 0);
+    assert_eq!(s.token.balance(&s.owner), DAILY_RATE * 5 + DEPOSIT * 3);
+    assert_eq!(s.client.get_asset_stats().total_earnings.get(s.token.address.clone()), Some(DAILY_RATE * 5 + DEPOSIT * 3));
+    assert_eq!(s.client.try_repay_debt(&lease_id, &s.lessee, &1), Err(Ok(LeasingError::NoDebtOutstanding)));
+}
+
+#[test]
+fn award_within_deposit_leaves_no_debt() {
+    let s = setup();
+    let lease_id = active_lease(&s, 5);
+    s.client.file_dispute(&lease_id, &s.owner, &String::from_str(&s.env, "Scratched"));
+    panel_rules(&s, lease_id, true, 100);
+
+    assert_eq!(s.token.balance(&s.owner), DAILY_RATE * 5 + DEPOSIT);
+    assert_eq!(s.client.try_get_debt(&lease_id), Err(Ok(LeasingError::NoDebtOutstanding)));
+    assert_eq!(s.client.try_repay_debt(&lease_id, &s.lessee, &0), Err(Ok(LeasingError::InvalidRepayment)));
+}
+
+#[test]
+fn dispute_ruling_bills_unpaid_installments_as_debt() {
+    for in_favor_of_lessor in [true, false] {
+        let s = setup();
+        let asset_id = register_asset(&s);
+        let lease_id = s.client.create_lease(&asset_id, &s.lessee, &10, &PaymentSchedule::Daily);
+        s.client.approve_lease(&lease_id, &s.owner);
+
+        // Only the first installment is paid before the dispute on day nine
+        let filed_at = s.client.get_lease(&lease_id).start_time + 9 * 24 * 60 * 60;
+        s.env.ledger().set_timestamp(filed_at);
+        assert_eq!(s.client.get_missed_installments(&lease_id), 8);
+        s.client.file_dispute(&lease_id, &s.lessee, &String::from_str(&s.env, "Broken"));
+        s.env.ledger().set_timestamp(filed_at + 24 * 60 * 60);
+        panel_rules(&s, lease_id, in_favor_of_lessor, 0);
+
+        // The paid day is settled now and the other eight days used are owed
+        assert_eq!(s.token.balance(&s.owner), DAILY_RATE);
+        assert_eq!(s.token.balance(&s.lessee), STARTING_BALANCE - DAILY_RATE);
+        let debt = s.client.get_debt(&lease_id);
+        assert_eq!(debt.amount, DAILY_RATE * 8);
+        assert_eq!(debt.debtor, s.lessee);
+        assert_eq!(debt.creditor, s.owner);
+        assert_consistent(&s);
+    }
+}
+
+// Active lease on an asset with a claim window, completed cleanly at the end of its term
+fn completed_with_hold(s: &Setup) -> u64 {
+    let asset_id = register_asset(s);
+    s.client.set_claim_window(&asset_id, &s.owner, &CLAIM_WINDOW, &HOLDBACK);
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &5, &PaymentSchedule::Upfront);
+    s.client.approve_lease(&lease_id, &s.owner);
+    advance_to_end(s, lease_id);
+    return_clean(s, lease_id);
+    lease_id
+}
+
+#[test]
+fn completion_holds_deposit_for_claim_window() {
+    let s = setup();
+    let lease_id = completed_with_hold(&s);
+
+    let lease = s.client.get_lease(&lease_id);
+    assert_eq!(lease.held_deposit, HOLDBACK);
+    assert_eq!(lease.claim_deadline, lease.end_time + CLAIM_WINDOW);
+    assert_eq!(s.token.balance(&s.lessee), STARTING_BALANCE - DAILY_RATE * 5 - HOLDBACK);
+    assert_eq!(s.token.balance(&s.client.address), HOLDBACK);
+    assert_eq!(s.client.get_asset_stats().total_value_locked.get(s.token.address.clone()), Some(HOLDBACK));
+
+    // Nothing is released while the owner can still claim
+    assert_eq!(s.client.try_release_held_deposit(&lease_id), Err(Ok(LeasingError::ClaimWindowOpen)));
+
+    s.env.ledger().set_timestamp(lease.claim_deadline + 1);
+    assert_eq!(s.client.release_held_deposit(&lease_id), HOLDBACK);
+    assert_eq!(s.token.balance(&s.lessee), STARTING_BALANCE - DAILY_RATE * 5);
+    assert_eq!(s.client.get_asset_stats().total_value_locked.get(s.token.address.clone()), Some(0));
+    assert_eq!(s.client.try_release_held_deposit(&lease_id), Err(Ok(LeasingError::ClaimWindowClosed)));
+    assert_eq!(
+        s.client.try_file_damage_claim(&lease_id, &s.owner, &String::from_str(&s.env, "Dent")),
+        Err(Ok(LeasingError::ClaimWindowClosed))
+    );
+}
+
+#[test]
+fn damage_claim_is_paid_from_held_deposit() {
+    let s = setup();
+    let lease_id = completed_with_hold(&s);
+
+    s.client.file_damage_claim(&lease_id, &s.owner, &String::from_str(&s.env, "Cracked bucket"));
+    assert_eq!(s.client.get_dispute(&lease_id).kind, DisputeKind::DamageClaim);
+    assert_eq!(s.client.get_asset_stats().disputed_leases, 1);
+
+    // An open claim keeps the deposit held even after the window
+    let deadline = s.client.get_lease(&lease_id).claim_deadline;
+    s.env.ledger().set_timestamp(deadline + 1);
+    assert_eq!(s.client.try_release_held_deposit(&lease_id), Err(Ok(LeasingError::ClaimPending)));
+
+    // 40% of the deposit is within the holdback; the remainder goes back to the lessee
+    panel_rules(&s, lease_id, true, 40);
+    let award = DEPOSIT * 40 / 100;
+    assert_eq!(s.token.balance(&s.owner), DAILY_RATE * 5 + award);
+    assert_eq!(s.token.balance(&s.lessee), STARTING_BALANCE - DAILY_RATE * 5 - award);
+    assert_eq!(s.token.balance(&s.client.address), 0);
+
+    let lease = s.client.get_lease(&lease_id);
+    assert_eq!(lease.status, LeaseStatus::Completed);
+    assert_eq!(lease.held_deposit, 0);
+    assert_eq!(lease.penalty_amount, award);
+    let stats = s.client.get_asset_stats();
+    assert_eq!(stats.disputed_leases, 0);
+    assert_eq!(stats.completed_leases, 1);
+}
+
+#[test]
+fn damage_claim_beyond_holdback_becomes_debt() {
+    let s = setup();
+    let lease_id = completed_with_hold(&s);
+    s.client.file_damage_claim(&lease_id, &s.owner, &String::from_str(&s.env, "Engine seized"));
+    assert_eq!(
+        s.client.try_file_damage_claim(&lease_id, &s.owner, &String::from_str(&s.env, "Again")),
+        Err(Ok(LeasingError::ClaimPending))
+    );
+
+    panel_rules(&s, lease_id, true, 100);
+    assert_eq!(s.token.balance(&s.owner), DAILY_RATE * 5 + HOLDBACK);
+    assert_eq!(s.client.get_debt(&lease_id).amount, DEPOSIT - HOLDBACK);
+}
+
+#[test]
+fn only_lessor_claims_damages() {
+    let s = setup();
+    let lease_id = completed_with_hold(&s);
+    assert_eq!(
+        s.client.try_file_damage_claim(&lease_id, &s.lessee, &String::from_str(&s.env, "Dent")),
+        Err(Ok(LeasingError::NotAssetOwner))
+    );
+
+    // Leases on assets without a window have nothing to claim against
+    let other = active_lease(&s, 5);
+    return_clean(&s, other);
+    assert_eq!(
+        s.client.try_file_damage_claim(&other, &s.owner, &String::from_str(&s.env, "Dent")),
+        Err(Ok(LeasingError::ClaimWindowClosed))
+    );
+}
+
+#[test]
+fn unanswered_return_auto_confirms() {
+    let s = setup();
+    let lease_id = active_lease(&s, 5);
+    advance_to_end(&s, lease_id);
+    s.client.return_asset(&lease_id, &s.lessee, &String::from_str(&s.env, "Clean"));
+
+    let lease = s.client.get_lease(&lease_id);
+    assert_eq!(lease.status, LeaseStatus::Returned);
+    assert_eq!(lease.return_condition, String::from_str(&s.env, "Clean"));
+    assert_eq!(s.client.try_finalize_return(&lease_id), Err(Ok(LeasingError::ConfirmationPending)));
+
+    s.env.ledger().set_timestamp(lease.confirm_deadline + 1);
+    assert_eq!(s.client.try_confirm_return(&lease_id, &s.owner, &0), Err(Ok(LeasingError::ConfirmationPeriodOver)));
+    s.client.finalize_return(&lease_id);
+
+    assert_eq!(s.client.get_lease(&lease_id).penalty_amount, 0);
+    assert_eq!(s.token.balance(&s.lessee), STARTING_BALANCE - DAILY_RATE * 5);
+    assert_eq!(s.client.get_asset_stats().completed_leases, 1);
+}
+
+#[test]
+fn unanswered_damage_report_stands() {
+    let s = setup();
+    let policy = PenaltyPolicy { damage: DamagePenalty::Fixed(DEPOSIT + 200), cap: DEPOSIT * 2, ..default_policy() };
+    let asset_id = register_asset_with_policy(&s, &policy);
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &5, &PaymentSchedule::Upfront);
+    s.client.approve_lease(&lease_id, &s.owner);
+    advance_to_end(&s, lease_id);
+    s.client.return_asset(&lease_id, &s.lessee, &String::from_str(&s.env, "Fine"));
+    s.client.confirm_return(&lease_id, &s.owner, &(DEPOSIT + 200));
+    assert_eq!(s.client.try_confirm_return(&lease_id, &s.owner, &0), Err(Ok(LeasingError::DamagesAlreadyReported)));
+
+    s.env.ledger().set_timestamp(s.client.get_lease(&lease_id).confirm_deadline + 1);
+    s.client.finalize_return(&lease_id);
+
+    // The deposit covers what it can and the rest is owed
+    assert_eq!(s.token.balance(&s.owner), DAILY_RATE * 5 + DEPOSIT);
+    assert_eq!(s.client.get_debt(&lease_id).amount, 200);
+}
+
+#[test]
+fn disputed_damage_report_goes_to_panel() {
+    let s = setup();
+    let lease_id = active_lease(&s, 10);
+
+    // Returned early after three days and disputed later; rent stops at the return
+    s.env.ledger().set_timestamp(3 * 24 * 60 * 60);
+    s.client.return_asset(&lease_id, &s.lessee, &String::from_str(&s.env, "Fine"));
+    s.client.confirm_return(&lease_id, &s.owner, &(DEPOSIT / 4));
+    s.env.ledger().set_timestamp(4 * 24 * 60 * 60);
+    s.client.file_dispute(&lease_id, &s.lessee, &String::from_str(&s.env, "No damage"));
+    assert_eq!(s.client.get_lease_status(&lease_id), LeaseStatus::Disputed);
+    assert_eq!(s.client.try_accept_damages(&lease_id, &s.lessee), Err(Ok(LeasingError::LeaseNotReturned)));
+
+    panel_rules(&s, lease_id, false, 0);
+    assert_eq!(s.token.balance(&s.owner), DAILY_RATE * 3);
+    assert_eq!(s.token.balance(&s.lessee), STARTING_BALANCE - DAILY_RATE * 3);
+    assert_eq!(s.client.get_asset_stats().active_leases, 0);
+}
+
+#[test]
+fn only_parties_drive_the_return() {
+    let s = setup();
+    let lease_id = active_lease(&s, 5);
+    assert_eq!(
+        s.client.try_return_asset(&lease_id, &s.owner, &String::from_str(&s.env, "Clean")),
+        Err(Ok(LeasingError::NotLessee))
+    );
+    assert_eq!(s.client.try_confirm_return(&lease_id, &s.owner, &0), Err(Ok(LeasingError::LeaseNotReturned)));
+
+    s.client.return_asset(&lease_id, &s.lessee, &String::from_str(&s.env, "Clean"));
+    assert_eq!(s.client.try_confirm_return(&lease_id, &s.lessee, &0), Err(Ok(LeasingError::NotAssetOwner)));
+    assert_eq!(s.client.try_confirm_return(&lease_id, &s.owner, &-1), Err(Ok(LeasingError::InvalidDamages)));
+    assert_eq!(s.client.try_accept_damages(&lease_id, &s.lessee), Err(Ok(LeasingError::NoDamagesReported)));
+}
+
+#[test]
+fn damages_above_policy_need_a_dispute() {
+    let s = setup();
+    let lease_id = active_lease(&s, 5);
+    advance_to_end(&s, lease_id);
+    s.client.return_asset(&lease_id, &s.lessee, &String::from_str(&s.env, "Dented"));
+
+    assert_eq!(s.client.try_confirm_return(&lease_id, &s.owner, &(DEPOSIT / 4 + 1)), Err(Ok(LeasingError::InvalidDamages)));
+    s.client.confirm_return(&lease_id, &s.owner, &(DEPOSIT / 4));
+}
+
+#[test]
+fn late_fee_respects_grace_period_and_small_rates() {
+    let s = setup();
+    let policy = PenaltyPolicy { late_fee_bps: 500, grace_period: 24 * 60 * 60, ..default_policy() };
+    let asset_id = register_asset_with_policy(&s, &policy);
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &5, &PaymentSchedule::Upfront);
+    s.client.approve_lease(&lease_id, &s.owner);
+    assert_eq!(s.client.get_lease(&lease_id).penalty_policy, policy);
+
+    let end_time = s.client.get_lease(&lease_id).end_time;
+    assert_eq!(s.client.preview_penalty(&lease_id, &end_time, &0), 0);
+    assert_eq!(s.client.preview_penalty(&lease_id, &(end_time + 24 * 60 * 60), &0), 0);
+
+    // 5% of a 100 daily rate per started day past the grace period
+    assert_eq!(s.client.preview_penalty(&lease_id, &(end_time + 24 * 60 * 60 + 1), &0), 5);
+    assert_eq!(s.client.preview_penalty(&lease_id, &(end_time + 4 * 24 * 60 * 60), &20), 15 + 20);
+    assert_eq!(s.client.preview_penalty(&lease_id, &(end_time + 1000 * 24 * 60 * 60), &0), DEPOSIT);
+    assert_eq!(s.client.try_preview_penalty(&lease_id, &end_time, &DEPOSIT), Err(Ok(LeasingError::InvalidDamages)));
+
+    s.env.ledger().set_timestamp(end_time + 4 * 24 * 60 * 60);
+    return_clean(&s, lease_id);
+    assert_eq!(s.client.get_lease(&lease_id).penalty_amount, 15);
+}
+
+#[test]
+fn late_fees_count_from_the_approved_term() {
+    let s = setup();
+    let asset_id = register_asset(&s);
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &2, &PaymentSchedule::Upfront);
+
+    // Approved long after the proposed term would have ended, then returned at once
+    let approved_at = 10 * 24 * 60 * 60;
+    s.env.ledger().set_timestamp(approved_at);
+    s.client.approve_lease(&lease_id, &s.owner);
+    assert_eq!(s.client.preview_penalty(&lease_id, &approved_at, &0), 0);
+    assert_eq!(s.client.preview_penalty(&lease_id, &(approved_at + 3 * 24 * 60 * 60), &0), DAILY_RATE / 10);
+
+    return_clean(&s, lease_id);
+    assert_eq!(s.client.get_lease(&lease_id).penalty_amount, 0);
+}
+
+#[test]
+fn sub_unit_late_fees_round_up() {
+    let s = setup();
+    let policy = PenaltyPolicy { late_fee_bps: 1, ..default_policy() };
+    let asset_id = register_asset_with_policy(&s, &policy);
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &5, &PaymentSchedule::Upfront);
+    s.client.approve_lease(&lease_id, &s.owner);
+
+    let end_time = s.client.get_lease(&lease_id).end_time;
+    assert_eq!(s.client.preview_penalty(&lease_id, &(end_time + 60), &0), 1);
+}
+
+#[test]
+fn register_rejects_invalid_penalty_policy() {
+    let s = setup();
+    let over = PenaltyPolicy { damage: DamagePenalty::Percentage(10_001), ..default_policy() };
+    let negative = PenaltyPolicy { damage: DamagePenalty::Fixed(-1), ..default_policy() };
+    for policy in [over, negative] {
+        assert_eq!(
+            s.client.try_register_asset(
+                &s.owner,
+                &String::from_str(&s.env, "Excavator"),
+                &String::from_str(&s.env, "20t tracked excavator"),
+                &s.token.address,
+                &50_000,
+                &DAILY_RATE,
+                &1,
+                &30,
+                &DEPOSIT,
+                &policy,
+            ),
+            Err(Ok(LeasingError::InvalidAssetParameters))
+        );
+    }
+}
+
+#[test]
+fn pauser_freezes_everything_but_settlement() {
+    let s = setup();
+    let pauser = Address::generate(&s.env);
+    s.client.grant_role(&s.admin, &Role::Pauser, &pauser);
+    let asset_id = register_asset(&s);
+    let pending = s.client.create_lease(&asset_id, &s.lessee, &5, &PaymentSchedule::Upfront);
+    let active = active_lease(&s, 5);
+
+    s.client.pause(&pauser);
+    assert!(s.client.get_pause_state().all);
+    assert!(s.client.is_paused(&Operation::CreateLease));
+    assert_eq!(s.client.try_approve_lease(&pending, &s.owner), Err(Ok(LeasingError::ContractPaused)));
+    assert_eq!(
+        s.client.try_create_lease(&asset_id, &s.lessee, &5, &PaymentSchedule::Upfront),
+        Err(Ok(LeasingError::ContractPaused))
+    );
+    assert_eq!(s.client.try_set_early_exit_fee(&asset_id, &s.owner, &10), Err(Ok(LeasingError::ContractPaused)));
+
+    // Refunds and returns still go through
+    s.client.cancel_lease(&pending, &s.lessee);
+    advance_to_end(&s, active);
+    return_clean(&s, active);
+    assert_eq!(s.token.balance(&s.lessee), STARTING_BALANCE - DAILY_RATE * 5);
+
+    s.client.unpause(&pauser);
+    assert!(!s.client.is_paused(&Operation::CreateLease));
+    register_asset(&s);
+}
+
+#[test]
+fn single_operation_can_be_paused() {
+    let s = setup();
+    s.client.pause_operation(&s.admin, &Operation::RegisterAsset);
+    assert_eq!(s.client.get_pause_state().operations.len(), 1);
+
+    assert!(s.client.is_paused(&Operation::RegisterAsset));
+    assert!(!s.client.is_paused(&Operation::CreateLease));
+    assert_eq!(
+        s.client.try_register_asset(
+            &s.owner,
+            &String::from_str(&s.env, "Excavator"),
+            &String::from_str(&s.env, "20t tracked excavator"),
+            &s.token.address,
+            &50_000,
+            &DAILY_RATE,
+            &1,
+            &30,
+            &DEPOSIT,
+            &default_policy(),
+        ),
+        Err(Ok(LeasingError::ContractPaused))
+    );
+
+    // A global unpause leaves individually paused operations frozen
+    s.client.pause(&s.admin);
+    s.client.unpause(&s.admin);
+    assert!(s.client.is_paused(&Operation::RegisterAsset));
+
+    s.client.unpause_operation(&s.admin, &Operation::RegisterAsset);
+    register_asset(&s);
+}
+
+#[test]
+fn only_pausers_can_pause() {
+    let s = setup();
+    assert_eq!(s.client.try_pause(&s.owner), Err(Ok(LeasingError::Unauthorized)));
+    assert_eq!(s.client.try_pause_operation(&s.lessee, &Operation::CreateLease), Err(Ok(LeasingError::Unauthorized)));
+    assert!(!s.client.get_pause_state().all);
+}
+
+// Original storage layouts, kept only to seed version 1 data
+#[contracttype]
+#[derive(Clone)]
+pub struct AssetV1 {
+    pub asset_id: u64,
+    pub owner: Address,
+    pub title: String,
+    pub description: String,
+    pub asset_value: i128,
+    pub daily_rate: i128,
+    pub available: bool,
+    pub min_lease_days: u64,
+    pub max_lease_days: u64,
+    pub security_deposit: i128,
+    pub created_at: u64,
+}
+
+#[contracttype]
+#[derive(Clone)]
+pub struct LeaseV1 {
+    pub lease_id: u64,
+    pub asset_id: u64,
+    pub lessor: Address,
+    pub lessee: Address,
+    pub start_time: u64,
+    pub end_time: u64,
+    pub total_amount: i128,
+    pub security_deposit: i128,
+    pub is_active: bool,
+    pub is_completed: bool,
+    pub is_disputed: bool,
+    pub last_payment: u64,
+    pub return_condition: String,
+    pub penalty_amount: i128,
+    pub daily_rate: i128,
+}
+
+#[contracttype]
+#[derive(Clone)]
+pub struct AssetStatsV1 {
+    pub total_assets: u64,
+    pub active_leases: u64,
+    pub completed_leases: u64,
+    pub disputed_leases: u64,
+    pub total_value_locked: i128,
+    pub total_earnings: i128,
+}
+
+#[test]
+fn migrate_rewrites_version_1_layout() {
+    let s = setup();
+    let asset = AssetV1 {
+        asset_id: 1,
+        owner: s.owner.clone(),
+        title: String::from_str(&s.env, "Excavator"),
+        description: String::from_str(&s.env, "20t tracked excavator"),
+        asset_value: 50_000,
+        daily_rate: DAILY_RATE,
+        available: false,
+        min_lease_days: 1,
+        max_lease_days: 30,
+        security_deposit: DEPOSIT,
+        created_at: 0,
+    };
+    let lease = LeaseV1 {
+        lease_id: 1,
+        asset_id: 1,
+        lessor: s.owner.clone(),
+        lessee: s.lessee.clone(),
+        start_time: 0,
+        end_time: 5 * 24 * 60 * 60,
+        total_amount: DAILY_RATE * 5,
+        security_deposit: DEPOSIT,
+        is_active: true,
+        is_completed: false,
+        is_disputed: true,
+        last_payment: 0,
+        return_condition: String::from_str(&s.env, ""),
+        penalty_amount: 0,
+        daily_rate: DAILY_RATE,
+    };
+    let stats = AssetStatsV1 {
+        total_assets: 1,
+        active_leases: 1,
+        completed_leases: 0,
+        disputed_leases: 1,
+        total_value_locked: 5_000,
+        total_earnings: 700,
+    };
+    s.env.as_contract(&s.client.address, || {
+        let storage = s.env.storage().instance();
+        storage.set(&AssetRegistry::Asset(1), &asset);
+        storage.set(&LeaseRegistry::Lease(1), &lease);
+        storage.set(&symbol_short!("ASSET_STS"), &stats);
+        storage.set(&symbol_short!("ASSET_CNT"), &1u64);
+        storage.set(&symbol_short!("LEASE_CNT"), &1u64);
+        storage.remove(&symbol_short!("SCHEMA"));
+    });
+    assert_eq!(s.client.get_schema_version(), 1);
+
+    assert!(!s.client.migrate(&s.admin, &s.token.address, &1));
+    assert_eq!(s.client.get_schema_version(), 1);
+    assert!(s.client.migrate(&s.admin, &s.token.address, &1));
+    assert_eq!(s.client.get_schema_version(), 3);
+
+    let migrated = s.client.get_asset(&1);
+    assert_eq!(migrated.title, asset.title);
+    assert_eq!(migrated.token, s.token.address);
+    assert_eq!(migrated.penalty_policy.cap, DEPOSIT);
+
+    // Nothing was escrowed in version 1, so the lease carries no deposit and the
+    // flagged dispute reopens as an active lease
+    let migrated = s.client.get_lease(&1);
+    assert_eq!(migrated.status, LeaseStatus::Active);
+    assert_eq!(migrated.security_deposit, 0);
+    assert_eq!(migrated.amount_paid, 0);
+    assert_eq!(migrated.total_amount, DAILY_RATE * 5);
+
+    let stats = s.client.get_asset_stats();
+    assert_eq!(stats.disputed_leases, 0);
+    assert_eq!(stats.total_value_locked.len(), 0);
+    assert_eq!(stats.total_earnings.get(s.token.address.clone()), Some(700));
+
+    // The migrated lease settles normally, collecting its rent on return
+    advance_to_end(&s, 1);
+    return_clean(&s, 1);
+    assert_eq!(s.token.balance(&s.owner), DAILY_RATE * 5);
+}
+
+#[test]
+fn upgrade_keeps_storage_across_wasm_versions() {
+    let s = setup();
+    let v2_hash = s.env.deployer().upload_contract_wasm(leasing_v2::WASM);
+    let probe_hash = s.env.deployer().upload_contract_wasm(upgrade_probe::WASM);
+
+    // Deploy the released build and give it some state
+    let contract_id = s.env.register(leasing_v2::WASM, ());
+    let v2 = leasing_v2::Client::new(&s.env, &contract_id);
+    v2.initialize(&s.admin);
+    let asset_id = v2.register_asset(
+        &s.owner,
+        &String::from_str(&s.env, "Excavator"),
+        &String::from_str(&s.env, "20t tracked excavator"),
+        &s.token.address,
+        &50_000,
+        &DAILY_RATE,
+        &1,
+        &30,
+        &DEPOSIT,
+        &leasing_v2::PenaltyPolicy {
+            damage: leasing_v2::DamagePenalty::Percentage(2_500),
+            late_fee_bps: 1_000,
+            grace_period: 0,
+            cap: DEPOSIT,
+        },
+    );
+    assert_eq!(v2.get_schema_version(), 2);
+
+    assert!(v2.try_upgrade(&s.owner, &probe_hash).is_err());
+    v2.upgrade(&s.admin, &probe_hash);
+
+    // The new code sees the data the old code wrote
+    let probe = upgrade_probe::Client::new(&s.env, &contract_id);
+    assert_eq!(probe.get_schema_version(), 2);
+    assert_eq!(probe.asset_title(&asset_id), String::from_str(&s.env, "Excavator"));
+    assert_ne!(v2_hash, probe_hash);
+}
+
+#[test]
+fn migrate_after_upgrading_a_released_build() {
+    let s = setup();
+    let v3_hash = s.env.deployer().upload_contract_wasm(leasing_v3::WASM);
+
+    // A version 2 deployment with a live lease and a staked arbitrator
+    let contract_id = s.env.register(leasing_v2::WASM, ());
+    let v2 = leasing_v2::Client::new(&s.env, &contract_id);
+    v2.initialize(&s.admin);
+    v2.configure_arbitration(
+        &s.admin,
+        &leasing_v2::ArbitrationConfig {
+            stake_token: s.stake_token.address.clone(),
+            min_stake: MIN_STAKE,
+            slash_amount: SLASH_AMOUNT,
+            panel_size: 1,
+            quorum: 1,
+            voting_period: VOTING_PERIOD,
+        },
+    );
+    let arbitrator = Address::generate(&s.env);
+    StellarAssetClient::new(&s.env, &s.stake_token.address).mint(&arbitrator, &MIN_STAKE);
+    v2.grant_role(&s.admin, &leasing_v2::Role::Arbitrator, &arbitrator);
+    v2.join_arbitrator_pool(&arbitrator, &MIN_STAKE);
+    let asset_id = v2.register_asset(
+        &s.owner,
+        &String::from_str(&s.env, "Excavator"),
+        &String::from_str(&s.env, "20t tracked excavator"),
+        &s.token.address,
+        &50_000,
+        &DAILY_RATE,
+        &1,
+        &30,
+        &DEPOSIT,
+        &leasing_v2::PenaltyPolicy {
+            damage: leasing_v2::DamagePenalty::Percentage(2_500),
+            late_fee_bps: 1_000,
+            grace_period: 0,
+            cap: DEPOSIT,
+        },
+    );
+    let lease_id = v2.create_lease(&asset_id, &s.lessee, &5, &leasing_v2::PaymentSchedule::Upfront);
+    v2.approve_lease(&lease_id, &s.owner);
+    let end_time = v2.get_lease(&lease_id).end_time;
+
+    v2.upgrade(&s.admin, &v3_hash);
+    let v3 = leasing_v3::Client::new(&s.env, &contract_id);
+    assert_eq!(v3.get_schema_version(), 2);
+
+    while !v3.migrate(&s.admin, &s.token.address, &1) {}
+    assert_eq!(v3.get_schema_version(), 3);
+    s.env.as_contract(&contract_id, || {
+        let storage = s.env.storage();
+        assert!(storage.persistent().has(&ArbitrationRegistry::Pool));
+        assert!(storage.persistent().has(&RoleRegistry::Member(Role::Arbitrator, arbitrator.clone())));
+        assert!(!storage.instance().has(&symbol_short!("ARB_POOL")));
+    });
+    assert_eq!(v3.get_arbitrator_pool(), Vec::from_array(&s.env, [arbitrator.clone()]));
+    assert!(v3.has_role(&leasing_v3::Role::Arbitrator, &arbitrator));
+
+    // The lease written by version 2 settles under version 3
+    s.env.ledger().set_timestamp(end_time);
+    v3.return_asset(&lease_id, &s.lessee, &String::from_str(&s.env, "Clean"));
+    v3.confirm_return(&lease_id, &s.owner, &0);
+    assert_eq!(v3.get_lease_status(&lease_id), leasing_v3::LeaseStatus::Completed);
+    assert_eq!(s.token.balance(&s.owner), DAILY_RATE * 5);
+    assert_eq!(s.token.balance(&s.lessee), STARTING_BALANCE - DAILY_RATE * 5);
+}
+
+#[test]
+fn upgrade_is_admin_only() {
+    let s = setup();
+    let hash = s.env.deployer().upload_contract_wasm(leasing_v2::WASM);
+    assert_eq!(s.client.try_upgrade(&s.owner, &hash), Err(Ok(LeasingError::Unauthorized)));
+
+    // The native contract can move onto the released build
+    s.client.upgrade(&s.admin, &hash);
+    let v2 = leasing_v2::Client::new(&s.env, &s.client.address);
+    assert_eq!(v2.get_admin(), s.admin);
+}
+
+// A stored lease and whether it has an unresolved dispute
+fn load_lease(s: &Setup, lease_id: u64) -> Option<(Lease, bool)> {
+    let lease = s.client.try_get_lease(&lease_id).ok()?.ok()?;
+    let disputed = matches!(s.client.try_get_dispute(&lease_id), Ok(Ok(dispute)) if dispute.resolved_at.is_none());
+    Some((lease, disputed))
+}
+
+// Check stats against a set of leases: counters match lease statuses, value locked
+// matches what each lease still holds in escrow, and the contract's balance is
+// exactly that plus the escrow of pending proposals
+fn assert_books(s: &Setup, leases: &[(Lease, bool)]) {
+    let (mut live, mut completed, mut disputed) = (0, 0, 0);
+    let (mut locked, mut pending) = (0, 0);
+    for (lease, dispute_open) in leases {
+        match lease.status {
+            LeaseStatus::Pending => pending += lease.security_deposit + lease.amount_paid,
+            LeaseStatus::Active | LeaseStatus::Returned | LeaseStatus::Disputed => {
+                live += 1;
+                locked += lease.amount_paid - lease.withdrawn + lease.security_deposit + lease.pending_extension_amount;
+            }
+            LeaseStatus::Completed => {
+                completed += 1;
+                locked += lease.held_deposit;
+            }
+            LeaseStatus::Canceled => {}
+        }
+        if *dispute_open {
+            disputed += 1;
+        }
+    }
+
+    let stats = s.client.get_asset_stats();
+    assert_eq!(stats.active_leases, live);
+    assert_eq!(stats.completed_leases, completed);
+    assert_eq!(stats.disputed_leases, disputed);
+    assert_eq!(stats.total_value_locked.get(s.token.address.clone()).unwrap_or(0), locked);
+    assert_eq!(s.token.balance(&s.client.address), locked + pending);
+}
+
+// Open leases (pending or live) are listed as active for both parties, completed
+// ones as completed, and canceled ones not at all
+fn assert_listed(s: &Setup, lease: &Lease) {
+    let open = !matches!(lease.status, LeaseStatus::Completed | LeaseStatus::Canceled);
+    let is_completed = lease.status == LeaseStatus::Completed;
+    let lessor = s.client.get_user_portfolio(&lease.lessor);
+    let lessee = s.client.get_user_portfolio(&lease.lessee);
+    assert_eq!(lessor.active_leases_as_lessor.contains(lease.lease_id), open);
+    assert_eq!(lessee.active_leases_as_lessee.contains(lease.lease_id), open);
+    assert_eq!(lessor.completed_leases.contains(lease.lease_id), is_completed);
+    assert_eq!(lessee.completed_leases.contains(lease.lease_id), is_completed);
+}
+
+// Check stats and portfolios against every stored asset and lease
+fn assert_consistent(s: &Setup) {
+    let mut asset_id = 1;
+    while let Ok(Ok(asset)) = s.client.try_get_asset(&asset_id) {
+        assert!(s.client.get_user_portfolio(&asset.owner).owned_assets.contains(asset_id));
+        asset_id += 1;
+    }
+    assert_eq!(s.client.get_asset_stats().total_assets, asset_id - 1);
+
+    let mut leases = std::vec::Vec::new();
+    while let Some(entry) = load_lease(s, leases.len() as u64 + 1) {
+        assert_listed(s, &entry.0);
+        leases.push(entry);
+    }
+    assert_books(s, &leases);
+}
+
+// The invocation a party authorizes when calling the leasing contract directly
+fn leasing_call(s: &Setup, function: &str, args: Vec<Val>) -> AuthorizedInvocation {
+    AuthorizedInvocation {
+        function: AuthorizedFunction::Contract((s.client.address.clone(), Symbol::new(&s.env, function), args)),
+        sub_invocations: std::vec![],
+    }
+}
+
+#[test]
+fn lifecycle_keeps_stats_and_portfolios_consistent() {
+    let s = setup();
+    let asset_id = register_asset(&s);
+    assert_consistent(&s);
+
+    // Pay daily, extend by two days, then return a day and a half late
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &5, &PaymentSchedule::Daily);
+    assert_consistent(&s);
+    s.client.approve_lease(&lease_id, &s.owner);
+    assert_consistent(&s);
+    s.client.pay_installment(&lease_id, &s.lessee);
+    assert_consistent(&s);
+    s.client.request_extension(&lease_id, &s.lessee, &2);
+    assert_consistent(&s);
+    s.client.approve_extension(&lease_id, &s.owner);
+    assert_consistent(&s);
+
+    let end_time = s.client.get_lease(&lease_id).end_time;
+    s.env.ledger().set_timestamp(end_time + 36 * 60 * 60);
+    s.client.return_asset(&lease_id, &s.lessee, &String::from_str(&s.env, "Scratched"));
+    assert_consistent(&s);
+    s.client.confirm_return(&lease_id, &s.owner, &0);
+    assert_consistent(&s);
+
+    // Two started days late at 10% of the daily rate
+    let late_fee = DAILY_RATE / 5;
+    assert_eq!(s.client.get_lease(&lease_id).penalty_amount, late_fee);
+    assert_eq!(s.token.balance(&s.owner), DAILY_RATE * 7 + late_fee);
+    assert_eq!(s.token.balance(&s.lessee), STARTING_BALANCE - DAILY_RATE * 7 - late_fee);
+    let stats = s.client.get_asset_stats();
+    assert_eq!(stats.total_earnings.get(s.token.address.clone()), Some(DAILY_RATE * 7 + late_fee));
+
+    // Withdrawn and rejected proposals leave no trace in the counters
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &3, &PaymentSchedule::Upfront);
+    assert_consistent(&s);
+    s.client.cancel_lease(&lease_id, &s.lessee);
+    assert_consistent(&s);
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &3, &PaymentSchedule::Upfront);
+    s.client.reject_lease(&lease_id, &s.owner);
+    assert_consistent(&s);
+
+    // A disputed lease stays active until its panel rules
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &3, &PaymentSchedule::Upfront);
+    s.client.approve_lease(&lease_id, &s.owner);
+    s.client.file_dispute(&lease_id, &s.owner, &String::from_str(&s.env, "Not returned"));
+    assert_consistent(&s);
+    panel_rules(&s, lease_id, true, 50);
+    assert_consistent(&s);
+    assert_eq!(s.client.get_asset_stats().completed_leases, 2);
+}
+
+#[test]
+fn claim_window_lifecycle_keeps_stats_consistent() {
+    let s = setup();
+    let lease_id = completed_with_hold(&s);
+    assert_consistent(&s);
+
+    s.client.file_damage_claim(&lease_id, &s.owner, &String::from_str(&s.env, "Cracked boom"));
+    assert_consistent(&s);
+    panel_rules(&s, lease_id, false, 0);
+    assert_consistent(&s);
+
+    let lease_id = completed_with_hold(&s);
+    s.env.ledger().set_timestamp(s.client.get_lease(&lease_id).claim_deadline + 1);
+    s.client.release_held_deposit(&lease_id);
+    assert_consistent(&s);
+}
+
+#[test]
+fn streamed_lease_keeps_stats_consistent() {
+    let s = setup();
+    let asset_id = register_asset(&s);
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &4, &PaymentSchedule::Stream);
+    s.client.approve_lease(&lease_id, &s.owner);
+
+    s.env.ledger().set_timestamp(24 * 60 * 60);
+    s.client.withdraw_accrued(&lease_id);
+    assert_consistent(&s);
+
+    // Handed back early, with damages the lessee accepts
+    s.env.ledger().set_timestamp(2 * 24 * 60 * 60);
+    s.client.return_asset(&lease_id, &s.lessee, &String::from_str(&s.env, "Dented"));
+    s.client.confirm_return(&lease_id, &s.owner, &50);
+    assert_consistent(&s);
+    s.client.accept_damages(&lease_id, &s.lessee);
+    assert_consistent(&s);
+    assert_eq!(s.token.balance(&s.owner), DAILY_RATE * 2 + 50);
+}
+
+#[test]
+fn lease_calls_are_authorized_by_the_acting_party() {
+    let s = setup();
+    let asset_id = register_asset(&s);
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &5, &PaymentSchedule::Upfront);
+
+    // The lessee authorizes the proposal together with the escrow transfer it makes
+    let mut create = leasing_call(
+        &s,
+        "create_lease",
+        (asset_id, s.lessee.clone(), 5u64, PaymentSchedule::Upfront).into_val(&s.env),
+    );
+    create.sub_invocations.push(AuthorizedInvocation {
+        function: AuthorizedFunction::Contract((
+            s.token.address.clone(),
+            symbol_short!("transfer"),
+            (s.lessee.clone(), s.client.address.clone(), DAILY_RATE * 5 + DEPOSIT).into_val(&s.env),
+        )),
+        sub_invocations: std::vec![],
+    });
+    assert_eq!(s.env.auths(), std::vec![(s.lessee.clone(), create)]);
+
+    s.client.approve_lease(&lease_id, &s.owner);
+    assert_eq!(
+        s.env.auths(),
+        std::vec![(s.owner.clone(), leasing_call(&s, "approve_lease", (lease_id, s.owner.clone()).into_val(&s.env)))]
+    );
+
+    let condition = String::from_str(&s.env, "Clean");
+    s.client.return_asset(&lease_id, &s.lessee, &condition);
+    assert_eq!(
+        s.env.auths(),
+        std::vec![(
+            s.lessee.clone(),
+            leasing_call(&s, "return_asset", (lease_id, s.lessee.clone(), condition).into_val(&s.env))
+        )]
+    );
+
+    s.client.confirm_return(&lease_id, &s.owner, &0);
+    assert_eq!(
+        s.env.auths(),
+        std::vec![(s.owner.clone(), leasing_call(&s, "confirm_return", (lease_id, s.owner.clone(), 0i128).into_val(&s.env)))]
+    );
+}
+
+#[test]
+fn calls_without_authorization_fail() {
+    let s = setup();
+    let asset_id = register_asset(&s);
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &5, &PaymentSchedule::Upfront);
+
+    // Drop the blanket auth mock so require_auth finds no signature
+    s.env.set_auths(&[]);
+    assert!(matches!(s.client.try_approve_lease(&lease_id, &s.owner), Err(Err(_))));
+    assert!(matches!(s.client.try_cancel_lease(&lease_id, &s.lessee), Err(Err(_))));
+    assert_eq!(s.client.get_lease(&lease_id).status, LeaseStatus::Pending);
+    assert_consistent(&s);
+}
+
+#[test]
+fn create_lease_rejects_out_of_range_days() {
+    let s = setup();
+    let asset_id = register_asset(&s);
+    for days in [0, 31] {
+        assert_eq!(
+            s.client.try_create_lease(&asset_id, &s.lessee, &days, &PaymentSchedule::Upfront),
+            Err(Ok(LeasingError::InvalidLeaseDuration))
+        );
+    }
+    assert_consistent(&s);
+}
+
+#[test]
+fn extension_errors() {
+    let s = setup();
+    let asset_id = register_asset(&s);
+    let lease_id = s.client.create_lease(&asset_id, &s.lessee, &5, &PaymentSchedule::Upfront);
+    assert_eq!(s.client.try_request_extension(&lease_id, &s.lessee, &1), Err(Ok(LeasingError::LeaseNotActive)));
+
+    s.client.approve_lease(&lease_id, &s.owner);
+    assert_eq!(s.client.try_approve_extension(&lease_id, &s.owner), Err(Ok(LeasingError::NoExtensionPending)));
+    s.client.request_extension(&lease_id, &s.lessee, &1);
+    assert_eq!(s.client.try_request_extension(&lease_id, &s.lessee, &1), Err(Ok(LeasingError::ExtensionPending)));
+    assert_eq!(s.client.try_renew_lease(&lease_id), Err(Ok(LeasingError::AutoRenewDisabled)));
+    assert_consistent(&s);
+}
+
+#[test]
+fn early_exit_fee_must_not_be_negative() {
+    let s = setup();
+    let asset_id = register_asset(&s);
+    assert_eq!(s.client.try_set_early_exit_fee(&asset_id, &s.owner, &-1), Err(Ok(LeasingError::InvalidFee)));
+    assert_eq!(s.client.get_asset(&asset_id).early_exit_fee, 0);
+}
+
+#[test]
+fn uninitialized_contract_has_no_admin_or_arbitration() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let client = AssetLeasingContractClient::new(&env, &env.register(AssetLeasingContract, ()));
+
+    assert_eq!(client.try_get_admin(), Err(Ok(LeasingError::NotInitialized)));
+    assert_eq!(client.try_get_arbitration_config(), Err(Ok(LeasingError::ArbitrationNotConfigured)));
+}
+
+#[test]
+fn arbitration_config_and_stake_are_validated() {
+    let s = setup();
+    let mut config = s.client.get_arbitration_config();
+    config.quorum = config.panel_size + 1;
+    assert_eq!(s.client.try_configure_arbitration(&s.admin, &config), Err(Ok(LeasingError::InvalidArbitrationConfig)));
+
+    let arbitrator = s.arbitrators.get(0).unwrap();
+    assert_eq!(s.client.try_join_arbitrator_pool(&arbitrator, &0), Err(Ok(LeasingError::InsufficientStake)));
+    assert_eq!(s.client.get_arbitrator(&arbitrator).stake, MIN_STAKE);
+    assert_eq!(s.stake_token.balance(&s.client.address), MIN_STAKE * 4);
+}
+
+#[test]
+fn evidence_is_limited_per_dispute() {
+    let s = setup();
+    let lease_id = active_lease(&s, 5);
+    s.client.file_dispute(&lease_id, &s.lessee, &String::from_str(&s.env, "Broken on delivery"));
+
+    let uri = String::from_str(&s.env, "ipfs://evidence");
+    for i in 0..MAX_EVIDENCE {
+        let hash = BytesN::from_array(&s.env, &[i as u8; 32]);
+        assert_eq!(s.client.submit_evidence(&lease_id, &s.lessee, &hash, &uri), i + 1);
+    }
+    let hash = BytesN::from_array(&s.env, &[0xff; 32]);
+    assert_eq!(
+        s.client.try_submit_evidence(&lease_id, &s.owner, &hash, &uri),
+        Err(Ok(LeasingError::EvidenceLimitReached))
+    );
+}
+
+#[test]
+fn migrate_rejects_unreadable_entries() {
+    let s = setup();
+
+    // A version 1 asset whose title was stored with the wrong type
+    let asset = AssetV1 {
+        asset_id: 1,
+        owner: s.owner.clone(),
+        title: String::from_str(&s.env, "Excavator"),
+        description: String::from_str(&s.env, "20t tracked excavator"),
+        asset_value: 50_000,
+        daily_rate: DAILY_RATE,
+        available: true,
+        min_lease_days: 1,
+        max_lease_days: 30,
+        security_deposit: DEPOSIT,
+        created_at: 0,
+    };
+    s.env.as_contract(&s.client.address, || {
+        let val: Val = asset.into_val(&s.env);
+        let mut raw = Map::<Symbol, Val>::try_from_val(&s.env, &val).unwrap();
+        raw.set(Symbol::new(&s.env, "title"), 7u64.into_val(&s.env));
+        let storage = s.env.storage().instance();
+        storage.set(&AssetRegistry::Asset(1), &raw);
+        storage.set(&symbol_short!("ASSET_CNT"), &1u64);
+        storage.remove(&symbol_short!("SCHEMA"));
+    });
+
+    assert_eq!(
+        s.client.try_migrate(&s.admin, &s.token.address, &10),
+        Err(Ok(LeasingError::UnsupportedLayout))
+    );
+    assert_eq!(s.client.get_schema_version(), 1);
+}
+
+// Small xorshift generator so a randomized run can be replayed from its seed
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    // Usually the party entitled to make the call, sometimes anyone at all
+    fn caller(&mut self, parties: &Vec<Address>, entitled: Option<Address>) -> Address {
+        match entitled {
+            Some(party) if self.below(5) > 0 => party,
+            _ => parties.get(self.below(parties.len() as u64) as u32).unwrap(),
+        }
+    }
+}
+
+// Drive the contract through a seeded sequence of random calls, mostly valid but
+// some not, by random parties and at random times. Failed calls roll back, so every
+// step must leave the books consistent. Each call only changes the lease it names
+// (or creates one), so the books are checked against a copy of the leases that is
+// refreshed one lease at a time, and against storage in full at the end.
+fn run_random_operations(seed: u64, steps: u32) {
+    let s = setup();
+    let mut rng = Rng(seed);
+
+    let second_lessee = Address::generate(&s.env);
+    let minter = StellarAssetClient::new(&s.env, &s.token.address);
+    minter.mint(&s.lessee, &(STARTING_BALANCE * 9));
+    minter.mint(&second_lessee, &(STARTING_BALANCE * 10));
+    let mut parties = Vec::new(&s.env);
+    parties.push_back(s.owner.clone());
+    parties.push_back(s.lessee.clone());
+    parties.push_back(second_lessee.clone());
+    let lessees = [s.lessee.clone(), second_lessee];
+
+    let schedules = [
+        PaymentSchedule::Upfront,
+        PaymentSchedule::Daily,
+        PaymentSchedule::Weekly,
+        PaymentSchedule::Stream,
+    ];
+    let reason = String::from_str(&s.env, "Random");
+    let mut leases: std::vec::Vec<(Lease, bool)> = std::vec::Vec::new();
+    for _ in 0..3 {
+        register_asset(&s);
+    }
+
+    for step in 0..steps {
+        // Mostly leases that are still in play; ids sometimes run one past the last
+        // created entry to hit the not-found paths
+        let asset_id = rng.below(s.client.get_asset_stats().total_assets + 1) + 1;
+        let count = leases.len() as u64;
+        let in_play: std::vec::Vec<u64> = leases
+            .iter()
+            .filter(|(lease, _)| lease.status != LeaseStatus::Canceled)
+            .map(|(lease, _)| lease.lease_id)
+            .collect();
+        let lease_id = if rng.below(10) == 0 {
+            count + 1
+        } else if !in_play.is_empty() && rng.below(4) > 0 {
+            in_play[rng.below(in_play.len() as u64) as usize]
+        } else {
+            rng.below(count.max(1)) + 1
+        };
+        let lease = s.client.try_get_lease(&lease_id).ok().and_then(|lease| lease.ok());
+        let lessor = lease.as_ref().map(|lease| lease.lessor.clone());
+        let lessee = lease.as_ref().map(|lease| lease.lessee.clone());
+
+        // Mostly an operation that fits the lease's status, otherwise any operation
+        let likely: &[u64] = match lease.as_ref().map(|lease| lease.status) {
+            None | Some(LeaseStatus::Canceled) => &[0, 1, 2, 3],
+            Some(LeaseStatus::Pending) => &[4, 5, 4, 5, 6, 7],
+            Some(LeaseStatus::Active) => &[8, 9, 10, 10, 11, 12, 13, 17, 22],
+            Some(LeaseStatus::Returned) => &[11, 14, 15, 16, 17, 22],
+            Some(LeaseStatus::Disputed) => &[11, 19, 20, 21, 22],
+            Some(LeaseStatus::Completed) => &[1, 11, 18, 19, 21, 22],
+        };
+        let operation = if rng.below(5) == 0 { rng.below(24) } else { likely[rng.below(likely.len() as u64) as usize] };
+
+        match operation {
+            0 => {
+                let owner = rng.caller(&parties, Some(s.owner.clone()));
+                let _ = s.client.try_register_asset(
+                    &owner,
+                    &reason,
+                    &reason,
+                    &s.token.address,
+                    &50_000,
+                    &(rng.below(4) as i128 * DAILY_RATE),
+                    &1,
+                    &30,
+                    &DEPOSIT,
+                    &default_policy(),
+                );
+            }
+            1..=3 => {
+                let lessee = lessees[rng.below(2) as usize].clone();
+                let days = rng.below(35);
+                let schedule = schedules[rng.below(4) as usize];
+                let _ = s.client.try_create_lease(&asset_id, &lessee, &days, &schedule);
+            }
+            4 | 5 => {
+                let _ = s.client.try_approve_lease(&lease_id, &rng.caller(&parties, lessor));
+            }
+            6 => {
+                let _ = s.client.try_reject_lease(&lease_id, &rng.caller(&parties, lessor));
+            }
+            7 => {
+                let _ = s.client.try_cancel_lease(&lease_id, &rng.caller(&parties, lessee));
+            }
+            8 => {
+                let _ = s.client.try_pay_installment(&lease_id, &rng.caller(&parties, lessee));
+            }
+            9 => {
+                let _ = s.client.try_withdraw_accrued(&lease_id);
+            }
+            10 => {
+                let extra_days = rng.below(5);
+                let _ = s.client.try_request_extension(&lease_id, &rng.caller(&parties, lessee), &extra_days);
+            }
+            11 => {
+                let lessor = rng.caller(&parties, lessor);
+                if rng.below(2) == 0 {
+                    let _ = s.client.try_approve_extension(&lease_id, &lessor);
+                } else {
+                    let _ = s.client.try_reject_extension(&lease_id, &lessor);
+                }
+            }
+            12 | 13 => {
+                let _ = s.client.try_return_asset(&lease_id, &rng.caller(&parties, lessee), &reason);
+            }
+            14 | 15 => {
+                let damages = rng.below(3) as i128 * 50;
+                let _ = s.client.try_confirm_return(&lease_id, &rng.caller(&parties, lessor), &damages);
+            }
+            16 => {
+                if rng.below(2) == 0 {
+                    let _ = s.client.try_accept_damages(&lease_id, &rng.caller(&parties, lessee));
+                } else {
+                    let _ = s.client.try_finalize_return(&lease_id);
+                }
+            }
+            17 => {
+                let filer = if rng.below(2) == 0 { lessor } else { lessee };
+                let _ = s.client.try_file_dispute(&lease_id, &rng.caller(&parties, filer), &reason);
+            }
+            18 => {
+                let _ = s.client.try_file_damage_claim(&lease_id, &rng.caller(&parties, lessor), &reason);
+            }
+            19 | 20 => {
+                let arbitrator = s.arbitrators.get(rng.below(s.arbitrators.len() as u64) as u32).unwrap();
+                let in_favor_of_lessor = rng.below(2) == 0;
+                let _ = s.client.try_cast_vote(&lease_id, &arbitrator, &in_favor_of_lessor, &rng.below(101));
+            }
+            21 => {
+                if rng.below(2) == 0 {
+                    let _ = s.client.try_finalize_dispute(&lease_id);
+                } else {
+                    let _ = s.client.try_release_held_deposit(&lease_id);
+                }
+            }
+            _ => {
+                // Let up to three days pass
+                let now = s.env.ledger().timestamp();
+                s.env.ledger().set_timestamp(now + rng.below(3 * 24 * 60 * 60));
+            }
+        }
+
+        let checked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            // The lease the call named, then any lease it created
+            for id in [lease_id, leases.len() as u64 + 1] {
+                if let Some(entry) = load_lease(&s, id) {
+                    assert_listed(&s, &entry.0);
+                    match leases.get_mut(id as usize - 1) {
+                        Some(cached) => *cached = entry,
+                        None => leases.push(entry),
+                    }
+                }
+            }
+            assert_books(&s, &leases);
+        }));
+        if checked.is_err() {
+            panic!("books inconsistent after seed {:#x} step {} (operation {} on lease {})", seed, step, operation, lease_id);
+        }
+    }
+    assert_consistent(&s);
+}
+
+#[test]
+fn random_operations_keep_stats_consistent() {
+    for seed in [0x5eed, 0xc0ffee, 0xdecade, 0xfacade] {
+        run_random_operations(seed, 120);
+    }
+}
@@ -0,0 +1,25 @@
+#![no_std]
+use soroban_sdk::{contract, contractimpl, contracttype, symbol_short, Env, Map, String, Symbol, Val, TryFromVal};
+
+// Same key layout as the leasing contract
+#[contracttype]
+pub enum AssetRegistry {
+    Asset(u64)
+}
+
+#[contract]
+pub struct UpgradeProbe;
+
+#[contractimpl]
+impl UpgradeProbe {
+    // Schema version the leasing contract stored before the upgrade
+    pub fn get_schema_version(env: Env) -> u32 {
+        env.storage().instance().get(&symbol_short!("SCHEMA")).unwrap_or(0)
+    }
+
+    // Title of an asset written by the leasing contract, read field by field
+    pub fn asset_title(env: Env, asset_id: u64) -> String {
+        let raw: Map<Symbol, Val> = env.storage().persistent().get(&AssetRegistry::Asset(asset_id)).unwrap();
+        String::try_from_val(&env, &raw.get(Symbol::new(&env, "title")).unwrap()).unwrap()
+    }
+}